@@ -1,36 +1,93 @@
+CREATE TABLE `t_dept` (
+                          `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id，0为平台租户',
+                          `name` varchar(64) NOT NULL COMMENT '部门名称',
+                          `parent_id` bigint NOT NULL DEFAULT '0' COMMENT '上级部门id，0为根部门',
+                          `sort` int NOT NULL DEFAULT '0' COMMENT '同级排序，升序',
+                          `leader_id` bigint DEFAULT NULL COMMENT '部门负责人用户id',
+                          `creator_id` bigint NOT NULL COMMENT '创建人id',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          `updater_id` bigint DEFAULT NULL COMMENT '更新人id',
+                          `update_time` timestamp NULL DEFAULT NULL COMMENT '更新时间',
+                          PRIMARY KEY (`uid`),
+                          KEY `t_dept_tenant_id_index` (`tenant_id`),
+                          KEY `t_dept_parent_id_index` (`parent_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='部门表';
+
 CREATE TABLE `t_menu` (
                           `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id，0为平台租户',
                           `value` varchar(32) NOT NULL COMMENT '权限值',
                           `name` varchar(64) NOT NULL COMMENT '权限名称',
                           `type` varchar(16) NOT NULL COMMENT '类型。menu：菜单，button：按钮',
+                          `parent_id` bigint NOT NULL DEFAULT '0' COMMENT '父菜单id，0为根菜单',
+                          `sort` int NOT NULL DEFAULT '0' COMMENT '同级排序，升序',
+                          `path` varchar(128) DEFAULT NULL COMMENT '前端路由地址',
+                          `component` varchar(128) DEFAULT NULL COMMENT '前端组件路径',
+                          `icon` varchar(64) DEFAULT NULL COMMENT '图标',
+                          `visible` tinyint(1) NOT NULL DEFAULT '1' COMMENT '是否显示。1：显示，0：隐藏',
                           `creator_id` bigint NOT NULL COMMENT '创建人id',
                           `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
                           `updater_id` bigint DEFAULT NULL COMMENT '更新人id',
                           `update_time` timestamp NULL DEFAULT NULL COMMENT '更新时间',
                           PRIMARY KEY (`uid`),
-                          UNIQUE KEY `t_menu_value_uindex` (`value`)
+                          UNIQUE KEY `t_menu_tenant_id_value_uindex` (`tenant_id`,`value`),
+                          KEY `t_menu_parent_id_index` (`parent_id`)
 ) ENGINE=InnoDB AUTO_INCREMENT=16 DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='权限表';
 
 CREATE TABLE `t_role` (
                           `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id，0为平台租户',
                           `value` varchar(32) NOT NULL COMMENT '角色值',
                           `name` varchar(64) NOT NULL COMMENT '角色名称',
+                          `data_scope` varchar(16) NOT NULL DEFAULT 'all' COMMENT '数据范围。all：全部，self：本人，dept：本部门，custom：指定部门',
                           `creator_id` bigint NOT NULL COMMENT '创建人id',
                           `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
                           `updater_id` bigint DEFAULT NULL COMMENT '更新人id',
                           `update_time` timestamp NULL DEFAULT NULL COMMENT '更新时间',
                           PRIMARY KEY (`uid`),
-                          UNIQUE KEY `t_role_value_uindex` (`value`)
+                          UNIQUE KEY `t_role_tenant_id_value_uindex` (`tenant_id`,`value`)
 ) ENGINE=InnoDB AUTO_INCREMENT=20 DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='角色表';
 
+CREATE TABLE `t_role_dept` (
+                               `role_id` bigint NOT NULL COMMENT '角色uid',
+                               `dept_id` bigint NOT NULL COMMENT '部门uid',
+                               `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                               UNIQUE KEY `t_role_dept_role_id_dept_id_uindex` (`role_id`,`dept_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='角色自定义数据范围部门关联表';
+
 CREATE TABLE `t_role_menu` (
                                `role_id` bigint NOT NULL COMMENT '用户uid',
                                `menu_id` bigint NOT NULL COMMENT '菜单uid',
+                               `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
                                UNIQUE KEY `t_user_menu_role_id_menu_id_uindex` (`role_id`,`menu_id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='角色权限关联表';
 
+CREATE TABLE `t_role_parent` (
+                               `role_id` bigint NOT NULL COMMENT '角色uid',
+                               `parent_id` bigint NOT NULL COMMENT '父角色uid，角色继承父角色的全部权限',
+                               `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                               UNIQUE KEY `t_role_parent_role_id_parent_id_uindex` (`role_id`,`parent_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='角色继承关系表';
+
+CREATE TABLE `t_tenant` (
+                          `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `code` varchar(32) NOT NULL COMMENT '租户编码，请求头X-Tenant中携带',
+                          `name` varchar(64) NOT NULL COMMENT '租户名称',
+                          `domain` varchar(128) DEFAULT NULL COMMENT '租户域名，按请求Host识别租户',
+                          `enabled` tinyint(1) NOT NULL DEFAULT '1' COMMENT '是否启用。1：启用，0：停用',
+                          `creator_id` bigint NOT NULL COMMENT '创建人id',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          `updater_id` bigint DEFAULT NULL COMMENT '更新人id',
+                          `update_time` timestamp NULL DEFAULT NULL COMMENT '更新时间',
+                          PRIMARY KEY (`uid`),
+                          UNIQUE KEY `t_tenant_code_uindex` (`code`),
+                          UNIQUE KEY `t_tenant_domain_uindex` (`domain`)
+) ENGINE=InnoDB AUTO_INCREMENT=1 DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='租户表';
+
 CREATE TABLE `t_user` (
                           `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '用户id',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id，0为平台租户',
                           `account` varchar(64) NOT NULL COMMENT '账号',
                           `name` varchar(64) NOT NULL COMMENT '用户名',
                           `password` varchar(128) NOT NULL COMMENT '密码',
@@ -39,13 +96,98 @@ CREATE TABLE `t_user` (
                           `updater_id` bigint DEFAULT NULL COMMENT '更新人id',
                           `update_time` timestamp NULL DEFAULT NULL COMMENT '更新时间',
                           `avatar` varchar(32) DEFAULT NULL COMMENT '头像fileId',
+                          `dept_id` bigint DEFAULT NULL COMMENT '所属部门id',
+                          `status` varchar(16) NOT NULL DEFAULT 'active' COMMENT '账号状态。active：正常，disabled：停用',
+                          `locked_until` timestamp NULL DEFAULT NULL COMMENT '锁定截止时间',
+                          `expire_time` timestamp NULL DEFAULT NULL COMMENT '账号过期时间',
+                          `mfa_enabled` tinyint(1) NOT NULL DEFAULT '0' COMMENT '是否启用两步验证',
+                          `mfa_secret` varchar(64) DEFAULT NULL COMMENT '两步验证密钥，base32编码',
+                          `phone` varchar(32) DEFAULT NULL COMMENT '手机号',
+                          `email` varchar(128) DEFAULT NULL COMMENT '邮箱',
                           PRIMARY KEY (`uid`),
-                          UNIQUE KEY `t_user_account_uindex` (`account`)
+                          UNIQUE KEY `t_user_tenant_id_account_uindex` (`tenant_id`,`account`),
+                          KEY `t_user_dept_id_index` (`dept_id`)
 ) ENGINE=InnoDB AUTO_INCREMENT=100 DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='用户表';
 
 CREATE TABLE `t_user_role` (
                                `user_id` bigint NOT NULL COMMENT '用户uid',
                                `role_id` bigint NOT NULL COMMENT '角色uid',
+                               `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
                                UNIQUE KEY `t_user_role_role_id_user_id_uindex` (`role_id`,`user_id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='用户角色关联表';
 
+CREATE TABLE `t_user_status_log` (
+                          `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                          `user_id` bigint NOT NULL COMMENT '用户id',
+                          `status` varchar(16) NOT NULL COMMENT '变更后的账号状态',
+                          `locked_until` timestamp NULL DEFAULT NULL COMMENT '变更后的锁定截止时间',
+                          `expire_time` timestamp NULL DEFAULT NULL COMMENT '变更后的账号过期时间',
+                          `reason` varchar(255) NOT NULL COMMENT '变更原因',
+                          `operator_id` bigint NOT NULL COMMENT '操作人id',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          PRIMARY KEY (`uid`),
+                          KEY `t_user_status_log_user_id_index` (`tenant_id`,`user_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='账号状态变更记录表';
+
+CREATE TABLE `t_login_log` (
+                          `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                          `user_id` bigint NULL DEFAULT NULL COMMENT '用户id，账号不存在时为空',
+                          `account` varchar(64) NOT NULL COMMENT '登录账号',
+                          `ip` varchar(64) NOT NULL COMMENT '客户端ip',
+                          `device` varchar(32) NOT NULL COMMENT '设备类型',
+                          `user_agent` varchar(512) NOT NULL COMMENT '客户端标识',
+                          `success` tinyint(1) NOT NULL COMMENT '是否登录成功',
+                          `message` varchar(255) NOT NULL COMMENT '登录结果说明',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          PRIMARY KEY (`uid`),
+                          KEY `t_login_log_account_index` (`tenant_id`,`account`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='登录日志表';
+
+CREATE TABLE `t_mfa_recovery_code` (
+                          `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                          `user_id` bigint NOT NULL COMMENT '用户id',
+                          `code_hash` varchar(64) NOT NULL COMMENT '恢复码sha256哈希',
+                          `used_time` timestamp NULL DEFAULT NULL COMMENT '使用时间，未使用时为空',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          PRIMARY KEY (`uid`),
+                          KEY `t_mfa_recovery_code_user_id_index` (`tenant_id`,`user_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='两步验证恢复码表';
+
+CREATE TABLE `t_file` (
+                          `uid` varchar(32) NOT NULL COMMENT '文件id',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                          `owner_id` bigint NOT NULL COMMENT '上传人id',
+                          `name` varchar(255) NOT NULL COMMENT '原始文件名',
+                          `size` bigint NOT NULL COMMENT '文件大小，单位字节',
+                          `mime` varchar(128) NOT NULL COMMENT '文件类型',
+                          `sha256` varchar(64) NOT NULL COMMENT '内容sha256，同时作为存储key',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          PRIMARY KEY (`uid`),
+                          KEY `t_file_owner_id_index` (`tenant_id`,`owner_id`),
+                          KEY `t_file_sha256_index` (`sha256`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='文件表';
+
+CREATE TABLE `t_oper_log` (
+                          `uid` bigint NOT NULL AUTO_INCREMENT COMMENT '主键',
+                          `tenant_id` bigint NOT NULL DEFAULT '0' COMMENT '所属租户id',
+                          `user_id` bigint NULL DEFAULT NULL COMMENT '操作人id，未登录时为空',
+                          `account` varchar(64) NULL DEFAULT NULL COMMENT '操作人账号',
+                          `method` varchar(16) NOT NULL COMMENT '请求方法',
+                          `path` varchar(255) NOT NULL COMMENT '请求路径',
+                          `perm` varchar(255) NULL DEFAULT NULL COMMENT '接口要求的权限',
+                          `query` varchar(2048) NULL DEFAULT NULL COMMENT '查询字符串，已脱敏',
+                          `params` text NULL COMMENT 'JSON请求体，已脱敏',
+                          `status` int NOT NULL COMMENT 'HTTP状态码',
+                          `code` int NULL DEFAULT NULL COMMENT '业务状态码',
+                          `message` varchar(255) NULL DEFAULT NULL COMMENT '结果说明',
+                          `ip` varchar(64) NOT NULL COMMENT '客户端ip',
+                          `latency` bigint NOT NULL COMMENT '耗时，单位毫秒',
+                          `bypass` tinyint(1) NOT NULL DEFAULT '0' COMMENT '是否为超级管理员跳过权限校验',
+                          `create_time` timestamp NOT NULL DEFAULT (now()) COMMENT '创建时间',
+                          PRIMARY KEY (`uid`),
+                          KEY `t_oper_log_create_time_index` (`tenant_id`,`create_time`),
+                          KEY `t_oper_log_user_id_index` (`tenant_id`,`user_id`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_0900_ai_ci COMMENT='操作日志表';
@@ -1,8 +1,9 @@
-use crate::future::ResFuture;
+use crate::future::AuthFuture;
 use axum::extract::Request;
 use axum::response::{IntoResponse, Response};
 use std::convert::Infallible;
 use std::fmt::Debug;
+use std::future::{ready, Future, Ready};
 use std::task::{Context, Poll};
 use tower_layer::Layer;
 use tower_service::Service;
@@ -13,18 +14,32 @@ pub trait Authenticator {
     fn authenticate<Body>(&self, req: &mut Request<Body>) -> Result<(), Self::Err>;
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct AuthenticatorLayer<Cxt>
+/// 异步认证。认证通过后返回请求，供后续中间件及handler使用
+pub trait AsyncAuthenticator<Body> {
+    type Err: IntoResponse;
+    type Future: Future<Output = Result<Request<Body>, Self::Err>>;
+    fn authenticate(&self, req: Request<Body>) -> Self::Future;
+}
+
+/// 同步认证适配为异步认证，已实现[`Authenticator`]的类型可直接使用
+impl<Body, Cxt> AsyncAuthenticator<Body> for Cxt
 where
-    Cxt: Authenticator + Clone,
+    Cxt: Authenticator,
 {
+    type Err = Cxt::Err;
+    type Future = Ready<Result<Request<Body>, Self::Err>>;
+
+    fn authenticate(&self, mut req: Request<Body>) -> Self::Future {
+        ready(Authenticator::authenticate(self, &mut req).map(|_| req))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AuthenticatorLayer<Cxt> {
     pub(crate) context: Cxt,
 }
 
-impl<Cxt> AuthenticatorLayer<Cxt>
-where
-Cxt: Authenticator + Clone,
-{
+impl<Cxt> AuthenticatorLayer<Cxt> {
     pub fn new(context: Cxt) -> Self {
         Self { context }
     }
@@ -32,7 +47,7 @@ Cxt: Authenticator + Clone,
 
 impl<Svc, Cxt> Layer<Svc> for AuthenticatorLayer<Cxt>
 where
-    Cxt: Authenticator + Clone,
+    Cxt: Clone,
 {
     type Service = AuthenticatorService<Svc, Cxt>;
 
@@ -45,18 +60,12 @@ where
 }
 
 #[derive(Clone, Copy, Debug)]
-pub struct AuthenticatorService<Svc, Cxt>
-where
-    Cxt: Authenticator + Clone,
-{
+pub struct AuthenticatorService<Svc, Cxt> {
     pub(crate) inner: Svc,
     pub(crate) context: Cxt,
 }
 
-impl<Svc, Cxt> AuthenticatorService<Svc, Cxt>
-where
-    Cxt: Authenticator + Clone,
-{
+impl<Svc, Cxt> AuthenticatorService<Svc, Cxt> {
     pub fn new(inner: Svc, context: Cxt) -> Self {
         Self { inner, context }
     }
@@ -66,27 +75,24 @@ where
     }
 }
 
-impl<Body, Svc, Cxt, Err> Service<Request<Body>> for AuthenticatorService<Svc, Cxt>
+impl<Body, Svc, Cxt> Service<Request<Body>> for AuthenticatorService<Svc, Cxt>
 where
-    Svc: Service<Request<Body>, Response = Response, Error = Infallible>,
+    Svc: Service<Request<Body>, Response = Response, Error = Infallible> + Clone,
     Svc::Error: Debug,
-    Cxt: Authenticator<Err = Err> + Clone,
-    Err: IntoResponse + Clone,
+    Cxt: AsyncAuthenticator<Body>,
 {
     type Response = Response;
     type Error = Svc::Error;
-    type Future = ResFuture<Err, Svc::Future>;
+    type Future = AuthFuture<Cxt::Future, Svc, Svc::Future>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
-        if let Err(e) = self.context.authenticate(&mut req) {
-            return ResFuture::err(e);
-        }
-
-        ResFuture::new(self.inner.call(req))
-
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // 已就绪的服务交给future，认证通过后再调用
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        AuthFuture::new(AsyncAuthenticator::authenticate(&self.context, req), inner)
     }
 }
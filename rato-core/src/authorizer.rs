@@ -1,11 +1,12 @@
-use std::convert::Infallible;
-use axum::extract::{Request};
+use crate::future::AuthFuture;
+use axum::extract::Request;
 use axum::response::{IntoResponse, Response};
+use std::convert::Infallible;
 use std::fmt::Debug;
+use std::future::{ready, Future, Ready};
 use std::task::{Context, Poll};
 use tower_layer::Layer;
 use tower_service::Service;
-use crate::future::ResFuture;
 
 /// 授权
 pub trait Authorizer {
@@ -13,18 +14,32 @@ pub trait Authorizer {
     fn authorize<Body>(&self, req: &mut Request<Body>) -> Result<(), Self::Err>;
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct AuthorizerLayer<Cxt>
+/// 异步授权。授权通过后返回请求，供后续中间件及handler使用
+pub trait AsyncAuthorizer<Body> {
+    type Err: IntoResponse;
+    type Future: Future<Output = Result<Request<Body>, Self::Err>>;
+    fn authorize(&self, req: Request<Body>) -> Self::Future;
+}
+
+/// 同步授权适配为异步授权，已实现[`Authorizer`]的类型可直接使用
+impl<Body, Cxt> AsyncAuthorizer<Body> for Cxt
 where
-    Cxt: Authorizer + Clone,
+    Cxt: Authorizer,
 {
+    type Err = Cxt::Err;
+    type Future = Ready<Result<Request<Body>, Self::Err>>;
+
+    fn authorize(&self, mut req: Request<Body>) -> Self::Future {
+        ready(Authorizer::authorize(self, &mut req).map(|_| req))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AuthorizerLayer<Cxt> {
     pub(crate) context: Cxt,
 }
 
-impl<Cxt> AuthorizerLayer<Cxt>
-where
-    Cxt: Authorizer + Clone,
-{
+impl<Cxt> AuthorizerLayer<Cxt> {
     pub fn new(context: Cxt) -> Self {
         Self { context }
     }
@@ -32,7 +47,7 @@ where
 
 impl<Svc, Cxt> Layer<Svc> for AuthorizerLayer<Cxt>
 where
-    Cxt: Authorizer + Clone,
+    Cxt: Clone,
 {
     type Service = AuthorizerService<Svc, Cxt>;
 
@@ -45,18 +60,12 @@ where
 }
 
 #[derive(Clone, Copy, Debug)]
-pub struct AuthorizerService<Svc, Cxt>
-where
-    Cxt: Authorizer + Clone,
-{
+pub struct AuthorizerService<Svc, Cxt> {
     pub(crate) inner: Svc,
     pub(crate) context: Cxt,
 }
 
-impl<Svc, Cxt> AuthorizerService<Svc, Cxt>
-where
-    Cxt: Authorizer + Clone,
-{
+impl<Svc, Cxt> AuthorizerService<Svc, Cxt> {
     pub fn new(inner: Svc, context: Cxt) -> Self {
         Self { inner, context }
     }
@@ -66,25 +75,24 @@ where
     }
 }
 
-impl<Body, Svc, Cxt, Err> Service<Request<Body>> for AuthorizerService<Svc, Cxt>
+impl<Body, Svc, Cxt> Service<Request<Body>> for AuthorizerService<Svc, Cxt>
 where
-    Svc: Service<Request<Body>, Response = Response, Error = Infallible>,
-    Svc:: Error: Debug,
-    Cxt: Authorizer<Err = Err> + Clone,
-    Err: IntoResponse + Clone,
+    Svc: Service<Request<Body>, Response = Response, Error = Infallible> + Clone,
+    Svc::Error: Debug,
+    Cxt: AsyncAuthorizer<Body>,
 {
-    type Response = Svc::Response;
+    type Response = Response;
     type Error = Svc::Error;
-    type Future = ResFuture<Err, Svc::Future>;
+    type Future = AuthFuture<Cxt::Future, Svc, Svc::Future>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
-        if let Err(e) = self.context.authorize(&mut req) {
-            return ResFuture::err(e);
-        }
-        ResFuture::new(self.inner.call(req))
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // 已就绪的服务交给future，授权通过后再调用
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        AuthFuture::new(AsyncAuthorizer::authorize(&self.context, req), inner)
     }
 }
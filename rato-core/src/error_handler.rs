@@ -1,7 +1,10 @@
 use axum::body::Body;
 use axum::extract::Request;
+use axum::http::{HeaderValue, Method, Uri};
 use axum::response::{IntoResponse, Response};
 use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use std::any::Any;
 use std::fmt::Debug;
 use std::panic;
 use std::panic::AssertUnwindSafe;
@@ -9,11 +12,33 @@ use std::task::{Context, Poll};
 use tower_layer::Layer;
 use tower_service::Service;
 
+/// 请求id请求头。未携带时自动生成，并在响应中返回
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// panic上下文。包含panic内容及发生panic的请求信息
+pub struct PanicContext {
+    pub method: Method,
+    pub uri: Uri,
+    pub request_id: String,
+    pub payload: Box<dyn Any + Send>,
+}
+
+impl PanicContext {
+    /// panic消息。仅支持`&str`及`String`类型的payload
+    pub fn message(&self) -> &str {
+        if let Some(msg) = self.payload.downcast_ref::<&str>() {
+            msg
+        } else if let Some(msg) = self.payload.downcast_ref::<String>() {
+            msg.as_str()
+        } else {
+            "未知异常"
+        }
+    }
+}
+
 /// 全局异常处理
 pub trait ErrorHandler {
-    // TODO 尚未找到req该如何实现Copy Trait
-    // fn msg<Body>(&self, req: &Request<Body>) -> impl IntoResponse;
-    fn msg(&self) -> impl IntoResponse;
+    fn msg(&self, context: PanicContext) -> impl IntoResponse;
 }
 
 #[derive(Clone)]
@@ -63,18 +88,45 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request<B>) -> Self::Future {
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
         let clone = self.inner.clone();
         let mut inner = std::mem::replace(&mut self.inner, clone);
-        let msg = self.context.msg().into_response();
+        let context = self.context.clone();
+        // 请求id，用于关联日志及异常响应
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| HeaderValue::to_str(id).ok())
+            .map(String::from)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+        let header = HeaderValue::from_str(request_id.as_str()).ok();
+        if let Some(header) = header.clone() {
+            req.headers_mut().insert(REQUEST_ID_HEADER, header);
+        }
+        let method = req.method().clone();
+        let uri = req.uri().clone();
+        // 同步调用阶段及future轮询阶段的panic均需捕获
+        let future = panic::catch_unwind(AssertUnwindSafe(|| inner.call(req)));
         Box::pin(async move {
-            let fut = AssertUnwindSafe(inner.call(req));
-            match panic::catch_unwind(|| {
-                tokio::task::block_in_place(move || tokio::runtime::Handle::current().block_on(fut))
-            }) {
-                Ok(res) => Ok(res.unwrap()),
-                Err(_) => Ok(msg),
+            let result = match future {
+                Ok(future) => AssertUnwindSafe(future).catch_unwind().await,
+                Err(payload) => Err(payload),
+            };
+            let mut res = match result {
+                Ok(res) => res?,
+                Err(payload) => context
+                    .msg(PanicContext {
+                        method,
+                        uri,
+                        request_id,
+                        payload,
+                    })
+                    .into_response(),
+            };
+            if let Some(header) = header {
+                res.headers_mut().insert(REQUEST_ID_HEADER, header);
             }
+            Ok(res)
         })
     }
 }
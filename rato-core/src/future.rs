@@ -1,59 +1,71 @@
+use axum::extract::Request;
 use axum::response::{IntoResponse, Response};
 use pin_project_lite::pin_project;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{ready, Context, Poll};
+use tower_service::Service;
 
 pin_project! {
-    #[project = ResFutureProj]
-    pub struct ResFuture<E, F> {
+    /// 认证/授权响应future。先等待认证/授权结果，通过后再调用内部服务；失败则直接返回错误响应
+    pub struct AuthFuture<AuthFut, Svc, SvcFut> {
         #[pin]
-        inner: FutureState<E, F>,
+        inner: FutureState<AuthFut, SvcFut>,
+        service: Option<Svc>,
     }
 }
 
-impl<Err1, Fut, Err2> Future for ResFuture<Err1, Fut>
+impl<AuthFut, Svc, SvcFut, Body, Err, SvcErr> Future for AuthFuture<AuthFut, Svc, SvcFut>
 where
-    Err1: IntoResponse + Clone,
-    Fut: Future<Output = Result<Response, Err2>>,
+    AuthFut: Future<Output = Result<Request<Body>, Err>>,
+    Err: IntoResponse,
+    Svc: Service<Request<Body>, Response = Response, Error = SvcErr, Future = SvcFut>,
+    SvcFut: Future<Output = Result<Response, SvcErr>>,
 {
-    type Output = Result<Response, Err2>;
+    type Output = Result<Response, SvcErr>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let pin = self.project().inner.project();
-        let poll = match pin {
-            FutureStatProj::Error { e } =>  {
-                e.clone().into_response()
-            },
-            FutureStatProj::Success { future } => ready!(future.poll(cx))?,
-        };
-        Poll::Ready(Ok(poll))
+        let mut this = self.project();
+        loop {
+            match this.inner.as_mut().project() {
+                FutureStateProj::Pending { future } => {
+                    let req = match ready!(future.poll(cx)) {
+                        Ok(req) => req,
+                        Err(e) => return Poll::Ready(Ok(e.into_response())),
+                    };
+                    let mut service = this
+                        .service
+                        .take()
+                        .expect("AuthFuture polled after completion");
+                    this.inner.set(FutureState::Success {
+                        future: service.call(req),
+                    });
+                }
+                FutureStateProj::Success { future } => return future.poll(cx),
+            }
+        }
     }
 }
 
 pin_project! {
-    #[project = FutureStatProj]
-    enum FutureState<Err, Fut> {
-      Error{
-            e: Err
+    #[project = FutureStateProj]
+    enum FutureState<AuthFut, SvcFut> {
+        Pending {
+            #[pin]
+            future: AuthFut
         },
-      Success {
+        Success {
             #[pin]
-            future: Fut
+            future: SvcFut
         }
     }
 }
 
-impl<Err, Fut> ResFuture<Err, Fut> {
-    pub(crate) fn err(e: Err) -> Self {
-        Self {
-            inner: FutureState::Error { e },
-        }
-    }
-
-    pub(crate) fn new(future: Fut) -> Self {
+impl<AuthFut, Svc, SvcFut> AuthFuture<AuthFut, Svc, SvcFut> {
+    pub(crate) fn new(future: AuthFut, service: Svc) -> Self {
         Self {
-            inner: FutureState::Success { future },
+            inner: FutureState::Pending { future },
+            service: Some(service),
         }
     }
 }
@@ -9,4 +9,8 @@ pub mod redis;
 #[cfg(feature = "db")]
 pub mod database;
 #[cfg(feature = "error_handler")]
-pub mod error_handler;
\ No newline at end of file
+pub mod error_handler;
+#[cfg(feature = "storage")]
+pub mod storage;
+#[cfg(feature = "oper_log")]
+pub mod oper_log;
@@ -0,0 +1,383 @@
+use async_trait::async_trait;
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderMap, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+use http_body_util::LengthLimitError;
+use serde_json::Value;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// 脱敏后的替换内容
+pub const REDACTED: &str = "******";
+
+/// 操作日志记录
+#[derive(Debug, Clone)]
+pub struct OperRecord<E> {
+    pub user_id: Option<i64>,
+    pub account: Option<String>,
+    // 接口要求的权限，多个时以`,`分隔
+    pub perm: Option<String>,
+    pub method: String,
+    pub path: String,
+    // 查询字符串，已脱敏
+    pub query: Option<String>,
+    // JSON请求体，已脱敏，其他类型的请求体不记录
+    pub params: Option<String>,
+    // HTTP状态码
+    pub status: u16,
+    // 响应体中的业务状态码及说明，非JSON响应时为空
+    pub code: Option<i64>,
+    pub message: Option<String>,
+    // 耗时，单位毫秒
+    pub latency: u64,
+    // 是否跳过了权限校验，如超级管理员
+    pub bypass: bool,
+    pub time: SystemTime,
+    // 请求开始时由[`OperLogWriter::prepare`]获取的信息
+    pub extra: E,
+}
+
+/// 操作日志写入
+#[async_trait]
+pub trait OperLogWriter: Send + Sync + 'static {
+    type Extra: Send + 'static;
+
+    /// 请求开始时调用，与请求在同一任务中执行。用于获取客户端ip、租户等请求相关的信息，
+    /// 写入在后台任务中执行，无法再读取请求的task local
+    fn prepare(&self, req: &Request) -> Self::Extra;
+
+    /// 批量写入，在后台任务中执行
+    async fn write(&self, records: Vec<OperRecord<Self::Extra>>);
+}
+
+/// 操作日志上下文。由日志中间件放入请求扩展，内层的认证及授权中间件补充用户及权限信息。
+/// 标记为跳过权限校验的请求无论请求方法均会记录
+#[derive(Debug, Clone, Default)]
+pub struct OperContext(Arc<Mutex<OperUser>>);
+
+#[derive(Debug, Clone, Default)]
+struct OperUser {
+    user_id: Option<i64>,
+    account: Option<String>,
+    perm: Option<String>,
+    bypass: bool,
+}
+
+impl OperContext {
+    pub fn user(&self, user_id: i64, account: Option<String>) {
+        if let Ok(mut user) = self.0.lock() {
+            user.user_id = Some(user_id);
+            user.account = account;
+        }
+    }
+
+    pub fn perm(&self, perm: String) {
+        if let Ok(mut user) = self.0.lock() {
+            user.perm = Some(perm);
+        }
+    }
+
+    /// 标记本次请求跳过了权限校验
+    pub fn bypass(&self) {
+        if let Ok(mut user) = self.0.lock() {
+            user.bypass = true;
+        }
+    }
+
+    fn take(&self) -> OperUser {
+        self.0.lock().map(|mut user| std::mem::take(&mut *user)).unwrap_or_default()
+    }
+}
+
+/// 操作日志配置
+#[derive(Debug, Clone)]
+pub struct OperLogOptions {
+    // 每批写入的最大条数
+    pub batch_size: usize,
+    // 未达到批量条数时的写入间隔
+    pub interval: Duration,
+    // 待写入队列容量，队列已满时丢弃新的日志
+    pub capacity: usize,
+    // 记录的查询字符串及请求体的最大字符数
+    pub max_params: usize,
+    // 需脱敏的字段，字段名与其中任一完全相同时脱敏，不区分大小写
+    pub sensitive: Vec<String>,
+    // 需脱敏的字段后缀，字段名的最后一个单词与其中任一相同时脱敏，不区分大小写。
+    // 单词按`_`、`-`及驼峰划分，如`password`匹配`password`、`old_password`及`oldPassword`
+    pub sensitive_suffixes: Vec<String>,
+}
+
+impl Default for OperLogOptions {
+    fn default() -> Self {
+        OperLogOptions {
+            batch_size: 100,
+            interval: Duration::from_secs(5),
+            capacity: 10_000,
+            max_params: 2000,
+            sensitive: vec![],
+            sensitive_suffixes: vec![],
+        }
+    }
+}
+
+/// 操作日志中间件，记录全部POST请求及跳过权限校验的请求
+pub struct OperLogLayer<W: OperLogWriter> {
+    writer: Arc<W>,
+    sender: mpsc::Sender<OperRecord<W::Extra>>,
+    options: Arc<OperLogOptions>,
+}
+
+impl<W: OperLogWriter> Clone for OperLogLayer<W> {
+    fn clone(&self) -> Self {
+        OperLogLayer {
+            writer: self.writer.clone(),
+            sender: self.sender.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<W: OperLogWriter> OperLogLayer<W> {
+    /// 创建中间件并启动后台写入任务，需在tokio运行时内调用
+    pub fn new(writer: W, options: OperLogOptions) -> Self {
+        let writer = Arc::new(writer);
+        let (sender, receiver) = mpsc::channel(options.capacity.max(1));
+        tokio::spawn(Self::run(
+            writer.clone(),
+            receiver,
+            options.batch_size.max(1),
+            options.interval,
+        ));
+        OperLogLayer {
+            writer,
+            sender,
+            options: Arc::new(options),
+        }
+    }
+
+    /// 后台写入。达到批量条数或间隔时间后写入，中间件全部释放后写入剩余日志并退出
+    async fn run(
+        writer: Arc<W>,
+        mut receiver: mpsc::Receiver<OperRecord<W::Extra>>,
+        batch_size: usize,
+        interval: Duration,
+    ) {
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut flushed = Instant::now();
+        loop {
+            let wait = interval.saturating_sub(flushed.elapsed());
+            match tokio::time::timeout(wait, receiver.recv()).await {
+                Ok(Some(record)) => batch.push(record),
+                Ok(None) => break,
+                Err(_) => {}
+            }
+            if batch.len() >= batch_size || flushed.elapsed() >= interval {
+                if !batch.is_empty() {
+                    writer.write(std::mem::take(&mut batch)).await;
+                }
+                flushed = Instant::now();
+            }
+        }
+        if !batch.is_empty() {
+            writer.write(batch).await;
+        }
+    }
+}
+
+impl<S, W: OperLogWriter> Layer<S> for OperLogLayer<W> {
+    type Service = OperLogService<S, W>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        OperLogService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+pub struct OperLogService<S, W: OperLogWriter> {
+    inner: S,
+    layer: OperLogLayer<W>,
+}
+
+impl<S: Clone, W: OperLogWriter> Clone for OperLogService<S, W> {
+    fn clone(&self) -> Self {
+        OperLogService {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, W> Service<Request> for OperLogService<S, W>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Debug + Send,
+    W: OperLogWriter,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let start = Instant::now();
+        let time = SystemTime::now();
+        let layer = self.layer.clone();
+        let context = OperContext::default();
+        req.extensions_mut().insert(context.clone());
+        let post = req.method() == Method::POST;
+        let extra = layer.writer.prepare(&req);
+        let method = req.method().to_string();
+        let path = req.uri().path().to_string();
+        let query = req
+            .uri()
+            .query()
+            .map(|query| redact_query(query, &layer.options));
+        Box::pin(async move {
+            let mut params = None;
+            if post && is_json(req.headers()) {
+                let (parts, body) = req.into_parts();
+                let bytes = match to_bytes(body, usize::MAX).await {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        let too_large = e.source().is_some_and(|e| e.is::<LengthLimitError>());
+                        return Ok(if too_large {
+                            StatusCode::PAYLOAD_TOO_LARGE.into_response()
+                        } else {
+                            StatusCode::BAD_REQUEST.into_response()
+                        });
+                    }
+                };
+                params = Some(redact_body(&bytes, &layer.options));
+                req = Request::from_parts(parts, Body::from(bytes));
+            }
+            let mut res = inner.call(req).await?;
+            let user = context.take();
+            if !post && !user.bypass {
+                return Ok(res);
+            }
+            let (mut code, mut message) = (None, None);
+            if is_json(res.headers()) {
+                let (parts, body) = res.into_parts();
+                let bytes = match to_bytes(body, usize::MAX).await {
+                    Ok(bytes) => bytes,
+                    Err(_) => return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+                };
+                if let Ok(value) = serde_json::from_slice::<Value>(&bytes) {
+                    code = value.get("code").and_then(Value::as_i64);
+                    message = value.get("msg").and_then(Value::as_str).map(String::from);
+                }
+                res = Response::from_parts(parts, Body::from(bytes));
+            }
+            let record = OperRecord {
+                user_id: user.user_id,
+                account: user.account,
+                perm: user.perm,
+                method,
+                path,
+                query,
+                params,
+                status: res.status().as_u16(),
+                code,
+                message,
+                latency: start.elapsed().as_millis() as u64,
+                bypass: user.bypass,
+                time,
+                extra,
+            };
+            // 队列已满时丢弃，不阻塞请求
+            let _ = layer.sender.try_send(record);
+            Ok(res)
+        })
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"))
+}
+
+fn is_sensitive(key: &str, options: &OperLogOptions) -> bool {
+    let word = last_word(key).to_lowercase();
+    let key = key.to_lowercase();
+    options.sensitive.iter().any(|field| field.to_lowercase() == key)
+        || options.sensitive_suffixes.iter().any(|suffix| suffix.to_lowercase() == word)
+}
+
+/// 字段名的最后一个单词，按`_`、`-`及小写到大写的驼峰边界划分
+fn last_word(key: &str) -> &str {
+    let word = key.rsplit(['_', '-']).next().unwrap_or(key);
+    let chars = word.char_indices().collect::<Vec<_>>();
+    let start = chars
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0].1.is_lowercase() && pair[1].1.is_uppercase())
+        .map_or(0, |pair| pair[1].0);
+    &word[start..]
+}
+
+fn truncate(value: String, max: usize) -> String {
+    match value.char_indices().nth(max) {
+        Some((index, _)) => value[..index].to_string(),
+        None => value,
+    }
+}
+
+/// 脱敏JSON中的敏感字段，包括嵌套的对象及数组
+pub fn redact(value: &mut Value, options: &OperLogOptions) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive(key, options) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value, options);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| redact(value, options)),
+        _ => {}
+    }
+}
+
+/// 脱敏请求体，无法解析为JSON时不记录内容
+pub fn redact_body(bytes: &[u8], options: &OperLogOptions) -> String {
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) => {
+            redact(&mut value, options);
+            truncate(value.to_string(), options.max_params)
+        }
+        Err(_) => String::new(),
+    }
+}
+
+/// 脱敏查询字符串，参数名按原样匹配
+pub fn redact_query(query: &str, options: &OperLogOptions) -> String {
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_sensitive(key, options) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    truncate(query, options.max_params)
+}
@@ -1,7 +1,7 @@
 use std::fmt::Display;
 use async_trait::async_trait;
 use bb8::{ManageConnection, Pool};
-use redis::{FromRedisValue, ToRedisArgs};
+use redis::{FromRedisValue, Script, ToRedisArgs};
 
 /// 简易使用Redis
 #[async_trait]
@@ -21,11 +21,29 @@ pub trait RedisPool {
         K: ToRedisArgs + Sync + Send,
         V: ToRedisArgs + Sync + Send;
 
+    /// key不存在时设置值及过期时间，设置成功返回true
+    async fn set_nx_ex<K, V>(&self, k: K, v: V, expire: u64) -> Result<bool, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        V: ToRedisArgs + Sync + Send;
+
+    /// 更新值并保留原有过期时间
+    async fn set_keep_ttl<K, V>(&self, k: K, v: V) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        V: ToRedisArgs + Sync + Send;
+
     async fn get<K, V>(&self, k: K) -> Result<V, Self::E>
     where
         K: ToRedisArgs + Sync + Send,
         V: Sync + Send + Display + FromRedisValue;
 
+    /// 获取值并删除，key不存在时返回None
+    async fn get_del<K, V>(&self, k: K) -> Result<Option<V>, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        V: Sync + Send + FromRedisValue;
+
     async fn del<K>(&self, k: K) -> Result<(), Self::E>
     where
         K: ToRedisArgs + Sync + Send;
@@ -34,4 +52,62 @@ pub trait RedisPool {
     where
         K: ToRedisArgs + Sync + Send;
 
+    async fn expire<K>(&self, k: K, expire: i64) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send;
+
+    /// 自增1，返回自增后的值
+    async fn incr<K>(&self, k: K) -> Result<i64, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send;
+
+    async fn sadd<K, M>(&self, k: K, m: M) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send;
+
+    async fn srem<K, M>(&self, k: K, m: M) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send;
+
+    async fn smembers<K, M>(&self, k: K) -> Result<Vec<M>, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: Sync + Send + FromRedisValue;
+
+    /// 有序集合添加成员
+    async fn zadd<K, M>(&self, k: K, m: M, score: i64) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send;
+
+    /// 删除有序集合的成员
+    async fn zrem<K, M>(&self, k: K, m: M) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send;
+
+    /// 删除有序集合中分数在[min, max]内的成员
+    async fn zrembyscore<K>(&self, k: K, min: i64, max: i64) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send;
+
+    /// 有序集合成员数
+    async fn zcard<K>(&self, k: K) -> Result<i64, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send;
+
+    /// 剩余过期时间，单位秒。key不存在时为-2，未设置过期时间时为-1
+    async fn ttl<K>(&self, k: K) -> Result<i64, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send;
+
+    /// 执行Lua脚本，脚本内的命令整体原子执行
+    async fn eval<K, A, V>(&self, script: &Script, keys: &[K], args: &[A]) -> Result<V, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        A: ToRedisArgs + Sync + Send,
+        V: Send + FromRedisValue;
+
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+/// 文件内容读取流
+pub type FileReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// 文件存储。按key保存文件内容，key由调用方生成，只能包含字母、数字、`_`及`-`
+#[async_trait]
+pub trait FileStorage: Send + Sync {
+    /// 保存内容，key已存在时不重复写入
+    async fn put(&self, key: &str, bytes: &[u8]) -> io::Result<()>;
+
+    /// 以本地文件的内容保存，key已存在时不重复写入。成功后本地文件不再保留
+    async fn put_file(&self, key: &str, path: &Path) -> io::Result<()>;
+
+    /// 读取从offset开始的len个字节
+    async fn read(&self, key: &str, offset: u64, len: u64) -> io::Result<FileReader>;
+
+    async fn exists(&self, key: &str) -> io::Result<bool>;
+
+    /// 内容的字节数
+    async fn size(&self, key: &str) -> io::Result<u64>;
+
+    /// 删除内容，key不存在时忽略
+    async fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+/// 本地文件系统存储。按key的前两个字符分目录，避免单个目录下文件过多
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        LocalStorage {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    /// key对应的文件路径，校验key防止路径穿越
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let valid = key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+        if key.len() < 3 || !valid {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "非法的存储key"));
+        }
+        Ok(self.root.join(&key[..2]).join(key))
+    }
+}
+
+#[async_trait]
+impl FileStorage for LocalStorage {
+    async fn put(&self, key: &str, bytes: &[u8]) -> io::Result<()> {
+        let path = self.path(key)?;
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        let dir = path.parent().unwrap_or(&self.root);
+        tokio::fs::create_dir_all(dir).await?;
+        // 先写入临时文件再重命名，避免读取到写入一半的内容
+        let temp = dir.join(format!(".{}.{}", key, uuid::Uuid::new_v4().simple()));
+        tokio::fs::write(&temp, bytes).await?;
+        if let Err(e) = tokio::fs::rename(&temp, &path).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn put_file(&self, key: &str, path: &Path) -> io::Result<()> {
+        let target = self.path(key)?;
+        if tokio::fs::try_exists(&target).await? {
+            return tokio::fs::remove_file(path).await;
+        }
+        let dir = target.parent().unwrap_or(&self.root);
+        tokio::fs::create_dir_all(dir).await?;
+        // 跨文件系统时无法重命名，先复制到同目录的临时文件
+        if tokio::fs::rename(path, &target).await.is_err() {
+            let temp = dir.join(format!(".{}.{}", key, uuid::Uuid::new_v4().simple()));
+            tokio::fs::copy(path, &temp).await?;
+            if let Err(e) = tokio::fs::rename(&temp, &target).await {
+                let _ = tokio::fs::remove_file(&temp).await;
+                return Err(e);
+            }
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn read(&self, key: &str, offset: u64, len: u64) -> io::Result<FileReader> {
+        let mut file = tokio::fs::File::open(self.path(key)?).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(Box::pin(file.take(len)))
+    }
+
+    async fn exists(&self, key: &str) -> io::Result<bool> {
+        tokio::fs::try_exists(self.path(key)?).await
+    }
+
+    async fn size(&self, key: &str) -> io::Result<u64> {
+        Ok(tokio::fs::metadata(self.path(key)?).await?.len())
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
@@ -2,12 +2,11 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse};
 use axum::routing::get;
 use axum::Router;
-use rato_core::error_handler::{ErrorHandler, GlobalErrorHandlerLayer};
-use std::io::Error;
+use rato_core::error_handler::{ErrorHandler, GlobalErrorHandlerLayer, PanicContext};
 use tokio::net::TcpListener;
 
 /// 测试panic，全局异常捕获
-async fn err() -> Result<impl IntoResponse, Error> {
+async fn err() -> Result<impl IntoResponse, StatusCode> {
     let _ = 1 / 0;
     Ok("测试错误")
 }
@@ -15,14 +14,18 @@ async fn err() -> Result<impl IntoResponse, Error> {
 #[derive(Clone)]
 pub struct AppState;
 
-/// 实现全局异常捕获trait，返回自定义消息
+/// 实现全局异常捕获trait，返回自定义消息及请求id
 impl ErrorHandler for AppState {
-    fn msg(&self) -> impl IntoResponse {
-        (StatusCode::INTERNAL_SERVER_ERROR, "全局异常消息")
+    fn msg(&self, context: PanicContext) -> impl IntoResponse {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("全局异常消息：{}，请求id：{}", context.message(), context.request_id),
+        )
     }
 }
 
-#[tokio::main]
+/// 单线程运行时同样可以捕获panic
+#[tokio::main(flavor = "current_thread")]
 async fn main() {
     let router = Router::new()
         .route("/test", get(err))
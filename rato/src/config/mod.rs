@@ -1,8 +1,25 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
 use config::{Config, Environment};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use rsa::pkcs8::der::{self, Decode};
+use rsa::pkcs8::spki::{ObjectIdentifier, SubjectPublicKeyInfoRef};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::utils::password::{PasswordAlgorithm, PasswordConfig};
+
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const PRIME256V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
 
 /// 全局变量配置
 #[derive(Debug, Clone, Deserialize)]
@@ -11,12 +28,240 @@ pub struct GlobalConfig {
     pub server_port: u32,
     // host
     pub server_host: String,
-    // jwt秘钥
+    // 信任的反向代理，格式为ip或CIDR，多个以逗号分隔。仅连接地址属于其中时读取X-Forwarded-For及X-Real-IP
+    pub trusted_proxies: Option<String>,
+    // jwt秘钥，HS256签名时使用
     pub jwt_secret: String,
+    // jwt签名算法。HS256（默认）、RS256、ES256或EdDSA
+    #[serde(default = "default_jwt_algorithm")]
+    pub jwt_algorithm: String,
+    // 当前签名秘钥的kid
+    pub jwt_kid: Option<String>,
+    // 当前签名私钥PEM文件路径，非对称签名时使用
+    pub jwt_private_key: Option<String>,
+    // 验签公钥PEM文件，格式为`kid=path`，多个以逗号分隔。需包含当前签名私钥对应的公钥
+    pub jwt_public_keys: Option<String>,
+    // 访问令牌有效期，单位秒
+    #[serde(default = "default_access_token_expire")]
+    pub access_token_expire: i64,
+    // 刷新令牌有效期，单位秒。同时也是登录会话有效期
+    #[serde(default = "default_refresh_token_expire")]
+    pub refresh_token_expire: i64,
     // 数据库url
     pub database_url: String,
     // redis url
-    pub redis_url: String
+    pub redis_url: String,
+    // 密码哈希算法。argon2id（默认）或bcrypt
+    #[serde(default = "default_password_algorithm")]
+    pub password_algorithm: PasswordAlgorithm,
+    // argon2内存开销，单位KiB
+    #[serde(default = "default_password_memory_cost")]
+    pub password_memory_cost: u32,
+    // argon2迭代次数
+    #[serde(default = "default_password_time_cost")]
+    pub password_time_cost: u32,
+    // argon2并行度
+    #[serde(default = "default_password_parallelism")]
+    pub password_parallelism: u32,
+    // bcrypt开销
+    #[serde(default = "default_password_bcrypt_cost")]
+    pub password_bcrypt_cost: u32,
+    // 超级管理员角色值，拥有该角色的用户跳过权限校验
+    #[serde(default = "default_super_admin_role")]
+    pub super_admin_role: String,
+    // 是否启用超级管理员。关闭后所有用户仅按显式授予的角色及权限校验
+    #[serde(default = "default_super_admin_enabled")]
+    pub super_admin_enabled: bool,
+    // 是否为登录用户授予基础权限（获取当前用户、前端路由、校验令牌及退出登录）。关闭后需通过菜单显式授予
+    #[serde(default = "default_basic_perms_enabled")]
+    pub basic_perms_enabled: bool,
+    // 创建租户时初始化的角色，格式为`value:name`，多个以逗号分隔。超级管理员角色总会初始化
+    #[serde(default = "default_tenant_roles")]
+    pub tenant_roles: String,
+    // 单个账号在统计窗口内允许的登录失败次数
+    #[serde(default = "default_login_account_failures")]
+    pub login_account_failures: i64,
+    // 单个ip在统计窗口内允许的登录失败次数
+    #[serde(default = "default_login_ip_failures")]
+    pub login_ip_failures: i64,
+    // 登录失败统计窗口，单位秒
+    #[serde(default = "default_login_failure_window")]
+    pub login_failure_window: i64,
+    // 首次锁定时长，单位秒。连续锁定时每次翻倍
+    #[serde(default = "default_login_lockout")]
+    pub login_lockout: i64,
+    // 最长锁定时长，单位秒
+    #[serde(default = "default_login_lockout_max")]
+    pub login_lockout_max: i64,
+    // 是否始终要求登录及注册校验验证码
+    #[serde(default)]
+    pub captcha_enabled: bool,
+    // 未始终启用时，账号或ip在统计窗口内登录失败达到该次数后要求验证码，0表示不启用
+    #[serde(default = "default_captcha_after_failures")]
+    pub captcha_after_failures: i64,
+    // 验证码类型，alphanumeric（字母数字）或arithmetic（算术）
+    #[serde(default = "default_captcha_type")]
+    pub captcha_type: String,
+    // 字母数字验证码的字符数
+    #[serde(default = "default_captcha_length")]
+    pub captcha_length: usize,
+    // 验证码有效期，单位秒
+    #[serde(default = "default_captcha_expire")]
+    pub captcha_expire: u64,
+    // 两步验证的发行方名称，显示在验证器应用中
+    #[serde(default = "default_mfa_issuer")]
+    pub mfa_issuer: String,
+    // 账号密码校验通过后，提交动态验证码的有效期，单位秒
+    #[serde(default = "default_mfa_pending_expire")]
+    pub mfa_pending_expire: i64,
+    // 上传文件的保存目录
+    #[serde(default = "default_upload_dir")]
+    pub upload_dir: String,
+    // 单次请求的最大字节数，分片上传时即单个分片的上限
+    #[serde(default = "default_body_limit")]
+    pub body_limit: usize,
+    // 分片大小，单位字节，不超过单次请求的上限
+    #[serde(default = "default_upload_chunk_size")]
+    pub upload_chunk_size: u64,
+    // 分片上传的文件大小上限，单位字节
+    #[serde(default = "default_upload_max_size")]
+    pub upload_max_size: u64,
+    // 分片上传任务的有效期，单位秒，过期未完成的分片会被清理
+    #[serde(default = "default_upload_expire")]
+    pub upload_expire: u64,
+    // 单个用户同时进行的分片上传任务数上限
+    #[serde(default = "default_upload_max_concurrent")]
+    pub upload_max_concurrent: u64,
+    // 操作日志每批写入的最大条数
+    #[serde(default = "default_oper_log_batch_size")]
+    pub oper_log_batch_size: usize,
+    // 操作日志未达到批量条数时的写入间隔，单位秒
+    #[serde(default = "default_oper_log_interval")]
+    pub oper_log_interval: u64,
+}
+
+fn default_jwt_algorithm() -> String {
+    "HS256".to_string()
+}
+
+fn default_access_token_expire() -> i64 {
+    30 * 60
+}
+
+fn default_refresh_token_expire() -> i64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_password_algorithm() -> PasswordAlgorithm {
+    PasswordAlgorithm::Argon2id
+}
+
+fn default_password_memory_cost() -> u32 {
+    19 * 1024
+}
+
+fn default_password_time_cost() -> u32 {
+    2
+}
+
+fn default_password_parallelism() -> u32 {
+    1
+}
+
+fn default_password_bcrypt_cost() -> u32 {
+    12
+}
+
+fn default_super_admin_role() -> String {
+    "admin".to_string()
+}
+
+fn default_super_admin_enabled() -> bool {
+    true
+}
+
+fn default_basic_perms_enabled() -> bool {
+    true
+}
+
+fn default_login_account_failures() -> i64 {
+    5
+}
+
+fn default_login_ip_failures() -> i64 {
+    20
+}
+
+fn default_login_failure_window() -> i64 {
+    15 * 60
+}
+
+fn default_login_lockout() -> i64 {
+    60
+}
+
+fn default_login_lockout_max() -> i64 {
+    60 * 60
+}
+
+fn default_captcha_after_failures() -> i64 {
+    3
+}
+
+fn default_captcha_type() -> String {
+    "alphanumeric".to_string()
+}
+
+fn default_captcha_length() -> usize {
+    4
+}
+
+fn default_captcha_expire() -> u64 {
+    2 * 60
+}
+
+fn default_mfa_issuer() -> String {
+    "rato".to_string()
+}
+
+fn default_mfa_pending_expire() -> i64 {
+    5 * 60
+}
+
+fn default_upload_dir() -> String {
+    "uploads".to_string()
+}
+
+fn default_body_limit() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_upload_chunk_size() -> u64 {
+    5 * 1024 * 1024
+}
+
+fn default_upload_max_size() -> u64 {
+    1024 * 1024 * 1024
+}
+
+fn default_upload_expire() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_upload_max_concurrent() -> u64 {
+    5
+}
+
+fn default_oper_log_batch_size() -> usize {
+    100
+}
+
+fn default_oper_log_interval() -> u64 {
+    5
+}
+
+fn default_tenant_roles() -> String {
+    "admin:管理员,user:普通用户".to_string()
 }
 
 impl GlobalConfig {
@@ -28,6 +273,165 @@ impl GlobalConfig {
             .expect("初始化环境变量失败");
         cfg.try_deserialize::<GlobalConfig>().expect("反序列化环境变量失败")
     }
+
+    /// 密码哈希配置
+    pub fn password_config(&self) -> PasswordConfig {
+        PasswordConfig {
+            algorithm: self.password_algorithm,
+            memory_cost: self.password_memory_cost,
+            time_cost: self.password_time_cost,
+            parallelism: self.password_parallelism,
+            bcrypt_cost: self.password_bcrypt_cost,
+        }
+    }
+
+    /// 创建租户时初始化的角色值及名称，包含超级管理员角色
+    pub fn seed_roles(&self) -> Vec<(String, String)> {
+        let mut roles = self
+            .tenant_roles
+            .split(',')
+            .filter_map(|item| {
+                let (value, name) = item.split_once(':').unwrap_or((item, item));
+                let value = value.trim();
+                (!value.is_empty()).then(|| (value.to_string(), name.trim().to_string()))
+            })
+            .collect::<Vec<_>>();
+        if !roles.iter().any(|(value, _)| *value == self.super_admin_role) {
+            roles.insert(0, (self.super_admin_role.clone(), self.super_admin_role.clone()));
+        }
+        roles
+    }
+}
+
+/// Jwt签名配置。支持HS256及RS256、ES256、EdDSA非对称签名，可同时配置多个验签公钥以便无停机轮换秘钥
+pub struct JwtConfig {
+    pub algorithm: Algorithm,
+    pub kid: Option<String>,
+    pub encoding_key: EncodingKey,
+    // 按kid索引的验签秘钥
+    pub decoding_keys: HashMap<String, (Algorithm, DecodingKey)>,
+    // HS256验签秘钥，令牌未携带kid时使用
+    pub secret_key: Option<DecodingKey>,
+    // 公开的验签公钥集合
+    pub jwks: Value,
+}
+
+impl JwtConfig {
+    /// 根据全局配置加载签名私钥及验签公钥
+    pub fn init(config: &GlobalConfig) -> Self {
+        let algorithm = Algorithm::from_str(&config.jwt_algorithm)
+            .unwrap_or_else(|_| panic!("不支持的Jwt算法：{}", &config.jwt_algorithm));
+        if algorithm == Algorithm::HS256 {
+            return Self::secret(&config.jwt_secret);
+        }
+        let private_key = Self::read(config.jwt_private_key.as_deref().expect("未配置Jwt签名私钥"));
+        let public_keys = config
+            .jwt_public_keys
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(|item| {
+                let (kid, path) = item.split_once('=').expect("Jwt公钥配置格式应为kid=path");
+                (kid.trim().to_string(), Self::read(path.trim()))
+            })
+            .collect::<Vec<_>>();
+        let kid = config.jwt_kid.as_deref().expect("未配置Jwt签名秘钥kid");
+        match Self::new(algorithm, kid, &private_key, public_keys) {
+            Ok(jwt) => {
+                tracing::info!("Jwt signing with {:?}, kid：{}", algorithm, kid);
+                jwt
+            }
+            Err(e) => panic!("初始化Jwt秘钥失败：{}", e),
+        }
+    }
+
+    /// HS256对称签名
+    pub fn secret(secret: &str) -> Self {
+        JwtConfig {
+            algorithm: Algorithm::HS256,
+            kid: None,
+            encoding_key: EncodingKey::from_secret(secret.as_ref()),
+            decoding_keys: HashMap::new(),
+            secret_key: Some(DecodingKey::from_secret(secret.as_ref())),
+            jwks: json!({ "keys": [] }),
+        }
+    }
+
+    /// 非对称签名。`public_keys`为(kid, 公钥PEM)列表，需包含签名私钥对应的公钥
+    pub fn new(
+        algorithm: Algorithm,
+        kid: &str,
+        private_key: &str,
+        public_keys: Vec<(String, String)>,
+    ) -> Result<Self, String> {
+        let encoding_key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(private_key.as_bytes()),
+            Algorithm::ES256 => EncodingKey::from_ec_pem(private_key.as_bytes()),
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(private_key.as_bytes()),
+            _ => return Err(format!("不支持的Jwt算法：{:?}", algorithm)),
+        }
+        .map_err(|e| e.to_string())?;
+        let mut decoding_keys = HashMap::new();
+        let mut jwks = vec![];
+        for (public_kid, pem) in public_keys {
+            let (public_algorithm, decoding_key, mut jwk) = Self::public_key(&pem)?;
+            jwk["kid"] = json!(public_kid);
+            jwks.push(jwk);
+            decoding_keys.insert(public_kid, (public_algorithm, decoding_key));
+        }
+        match decoding_keys.get(kid) {
+            Some((public_algorithm, _)) if *public_algorithm == algorithm => {}
+            _ => return Err(format!("未找到签名秘钥{}对应的{:?}公钥", kid, algorithm)),
+        }
+        Ok(JwtConfig {
+            algorithm,
+            kid: Some(kid.to_string()),
+            encoding_key,
+            decoding_keys,
+            secret_key: None,
+            jwks: json!({ "keys": jwks }),
+        })
+    }
+
+    /// 解析SPKI格式的公钥PEM，返回算法、验签秘钥及JWK
+    fn public_key(pem: &str) -> Result<(Algorithm, DecodingKey, Value), String> {
+        let (_, der) = der::pem::decode_vec(pem.as_bytes()).map_err(|e| e.to_string())?;
+        let spki = SubjectPublicKeyInfoRef::from_der(&der).map_err(|e| e.to_string())?;
+        let raw = spki.subject_public_key.raw_bytes();
+        match spki.algorithm.oid {
+            RSA_ENCRYPTION => {
+                let key = RsaPublicKey::from_public_key_der(&der).map_err(|e| e.to_string())?;
+                let n = URL_SAFE_NO_PAD.encode(key.n().to_bytes_be());
+                let e = URL_SAFE_NO_PAD.encode(key.e().to_bytes_be());
+                let decoding_key = DecodingKey::from_rsa_components(&n, &e).map_err(|e| e.to_string())?;
+                let jwk = json!({ "kty": "RSA", "use": "sig", "alg": "RS256", "n": n, "e": e });
+                Ok((Algorithm::RS256, decoding_key, jwk))
+            }
+            EC_PUBLIC_KEY => {
+                // 未压缩的P-256公钥：0x04 || x || y
+                if spki.algorithm.parameters_oid().ok() != Some(PRIME256V1) || raw.len() != 65 {
+                    return Err("仅支持P-256椭圆曲线公钥".to_string());
+                }
+                let x = URL_SAFE_NO_PAD.encode(&raw[1..33]);
+                let y = URL_SAFE_NO_PAD.encode(&raw[33..65]);
+                let decoding_key = DecodingKey::from_ec_components(&x, &y).map_err(|e| e.to_string())?;
+                let jwk = json!({ "kty": "EC", "use": "sig", "alg": "ES256", "crv": "P-256", "x": x, "y": y });
+                Ok((Algorithm::ES256, decoding_key, jwk))
+            }
+            ED25519 => {
+                let x = URL_SAFE_NO_PAD.encode(raw);
+                let decoding_key = DecodingKey::from_ed_components(&x).map_err(|e| e.to_string())?;
+                let jwk = json!({ "kty": "OKP", "use": "sig", "alg": "EdDSA", "crv": "Ed25519", "x": x });
+                Ok((Algorithm::EdDSA, decoding_key, jwk))
+            }
+            oid => Err(format!("不支持的公钥算法：{}", oid)),
+        }
+    }
+
+    fn read(path: &str) -> String {
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("读取Jwt秘钥文件{}失败：{}", path, e))
+    }
 }
 
 /// Redis配置
@@ -1,2 +1,135 @@
+use crate::utils::tenant::TenantUtils;
+
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
-pub const LOGIN_UID: &str = "login_uid";
\ No newline at end of file
+pub const SESSION: &str = "session";
+pub const USER_SESSIONS: &str = "user_sessions";
+pub const ACCOUNT_STATE: &str = "account_state";
+pub const PERM_VERSION: &str = "perm_version";
+pub const USER_PERM: &str = "user_perm";
+pub const REFRESH_TOKEN: &str = "refresh_token";
+pub const REFRESH_FAMILY: &str = "refresh_family";
+pub const TENANT: &str = "tenant";
+pub const LOGIN_FAILURES: &str = "login_failures";
+pub const LOGIN_LOCK: &str = "login_lock";
+pub const LOGIN_LOCK_LEVEL: &str = "login_lock_level";
+pub const CAPTCHA: &str = "captcha";
+pub const MFA_ENROLL: &str = "mfa_enroll";
+pub const MFA_PENDING: &str = "mfa_pending";
+pub const MFA_STEP: &str = "mfa_step";
+pub const CHUNK_UPLOAD: &str = "chunk_upload";
+pub const CHUNK_PARTS: &str = "chunk_parts";
+pub const CHUNK_UPLOADS: &str = "chunk_uploads";
+pub const CHUNK_LOCK: &str = "chunk_lock";
+pub const FILE_LOCK: &str = "file_lock";
+// 租户编码请求头
+pub const TENANT_HEADER: &str = "X-Tenant";
+
+/// Redis缓存key。除租户缓存外均以当前租户id为前缀，各租户的会话及权限缓存互不可见
+pub struct RedisKey;
+
+impl RedisKey {
+    /// 当前租户的key前缀。`rato:{tenant}`
+    fn prefix() -> String {
+        format!("{}:{}", APP_NAME, TenantUtils::current())
+    }
+
+    /// 登录会话，以会话id为key。`rato:{tenant}:session:{sid}`
+    pub fn session(sid: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), SESSION, sid)
+    }
+
+    /// 用户的会话id集合。`rato:{tenant}:user_sessions:{uid}`
+    pub fn user_sessions(uid: i64) -> String {
+        format!("{}:{}:{}", Self::prefix(), USER_SESSIONS, uid)
+    }
+
+    /// 账号状态缓存。`rato:{tenant}:account_state:{uid}`
+    pub fn account_state(uid: i64) -> String {
+        format!("{}:{}:{}", Self::prefix(), ACCOUNT_STATE, uid)
+    }
+
+    /// 刷新令牌记录，以令牌哈希为key。`rato:{tenant}:refresh_token:{hash}`
+    pub fn refresh_token(hash: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), REFRESH_TOKEN, hash)
+    }
+
+    /// 刷新令牌族，保存当前有效的刷新令牌哈希。`rato:{tenant}:refresh_family:{family}`
+    pub fn refresh_family(family: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), REFRESH_FAMILY, family)
+    }
+
+    /// 权限版本号，角色、菜单及其关联变更时自增。`rato:{tenant}:perm_version`
+    pub fn perm_version() -> String {
+        format!("{}:{}", Self::prefix(), PERM_VERSION)
+    }
+
+    /// 用户角色及权限缓存。`rato:{tenant}:user_perm:{uid}`
+    pub fn user_perm(uid: i64) -> String {
+        format!("{}:{}:{}", Self::prefix(), USER_PERM, uid)
+    }
+
+    /// 登录失败记录，有序集合，分数为失败时间。subject为`account:{account}`或`ip:{ip}`。`rato:{tenant}:login_failures:{subject}`
+    pub fn login_failures(subject: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), LOGIN_FAILURES, subject)
+    }
+
+    /// 登录锁定，过期即解锁。`rato:{tenant}:login_lock:{subject}`
+    pub fn login_lock(subject: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), LOGIN_LOCK, subject)
+    }
+
+    /// 连续锁定次数，用于计算下一次锁定时长。`rato:{tenant}:login_lock_level:{subject}`
+    pub fn login_lock_level(subject: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), LOGIN_LOCK_LEVEL, subject)
+    }
+
+    /// 验证码答案，校验后即删除。`rato:{tenant}:captcha:{captcha_id}`
+    pub fn captcha(captcha_id: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), CAPTCHA, captcha_id)
+    }
+
+    /// 待确认的两步验证密钥。`rato:{tenant}:mfa_enroll:{uid}`
+    pub fn mfa_enroll(uid: i64) -> String {
+        format!("{}:{}:{}", Self::prefix(), MFA_ENROLL, uid)
+    }
+
+    /// 待两步验证的登录，以临时令牌哈希为key。`rato:{tenant}:mfa_pending:{hash}`
+    pub fn mfa_pending(hash: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), MFA_PENDING, hash)
+    }
+
+    /// 最近一次使用的动态验证码时间步，用于拒绝重放。`rato:{tenant}:mfa_step:{uid}`
+    pub fn mfa_step(uid: i64) -> String {
+        format!("{}:{}:{}", Self::prefix(), MFA_STEP, uid)
+    }
+
+    /// 分片上传任务。`rato:{tenant}:chunk_upload:{upload_id}`
+    pub fn chunk_upload(upload_id: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), CHUNK_UPLOAD, upload_id)
+    }
+
+    /// 分片上传任务已接收的分片序号集合。`rato:{tenant}:chunk_parts:{upload_id}`
+    pub fn chunk_parts(upload_id: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), CHUNK_PARTS, upload_id)
+    }
+
+    /// 用户进行中的分片上传任务，按任务过期时间排序。`rato:{tenant}:chunk_uploads:{owner_id}`
+    pub fn chunk_uploads(owner_id: i64) -> String {
+        format!("{}:{}:{}", Self::prefix(), CHUNK_UPLOADS, owner_id)
+    }
+
+    /// 分片上传任务的合并锁。`rato:{tenant}:chunk_lock:{upload_id}`
+    pub fn chunk_lock(upload_id: &str) -> String {
+        format!("{}:{}:{}", Self::prefix(), CHUNK_LOCK, upload_id)
+    }
+
+    /// 文件内容锁，存储不区分租户，锁同样不区分租户。`rato:file_lock:{sha256}`
+    pub fn file_lock(sha256: &str) -> String {
+        format!("{}:{}:{}", APP_NAME, FILE_LOCK, sha256)
+    }
+
+    /// 租户缓存，不区分租户。`rato:tenant:{tenant_id}`
+    pub fn tenant(tenant_id: i64) -> String {
+        format!("{}:{}:{}", APP_NAME, TENANT, tenant_id)
+    }
+}
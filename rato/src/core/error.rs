@@ -1,13 +1,15 @@
 use axum::extract::multipart::MultipartError;
 use crate::core::result::R;
 use axum::extract::rejection::JsonRejection;
-use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use base64::DecodeError;
 use derive_builder::{UninitializedFieldError};
 use jsonwebtoken::errors::{Error as JwtError, ErrorKind};
 use redis::{FromRedisValue, RedisError, RedisResult, Value};
 use sea_orm::{DbErr, SqlxError};
+use validator::ValidationErrors;
 
 /// 错误类型
 #[derive(Debug)]
@@ -18,6 +20,8 @@ pub enum AppError {
     Unauthorized,
     NotFound,
     Other(&'static str),
+    // 尝试次数过多，参数为建议的重试等待秒数
+    TooManyAttempts(i64),
     Unknown(anyhow::Error),
     JsonRejection(JsonRejection),
     SqlError(SqlxError),
@@ -27,6 +31,7 @@ pub enum AppError {
     JwtError(JwtError),
     DecodeError(DecodeError),
     MultipartError(MultipartError),
+    ValidationError(ValidationErrors),
 }
 
 /// 实现IntoResponse，可直接返回AppError
@@ -170,6 +175,13 @@ impl IntoResponse for AppError {
                 tracing::error!("{:?}", e);
                 R::fail("Base64解码异常")
             }
+            AppError::TooManyAttempts(retry_after) => {
+                tracing::warn!("尝试次数过多，{}秒后重试", retry_after);
+                let msg = format!("尝试次数过多，请{}秒后重试", retry_after);
+                let mut response = R::<String>::new(false, 429, None, &msg).into_response();
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+                return response;
+            }
             AppError::Other(msg) => {
                 tracing::error!("{}", msg);
                 R::fail(msg)
@@ -178,6 +190,21 @@ impl IntoResponse for AppError {
                 tracing::error!("{:?}", e);
                 R::fail("处理multipart失败")
             }
+            AppError::ValidationError(e) => {
+                tracing::error!("{:?}", e);
+                let mut errors = e
+                    .field_errors()
+                    .into_iter()
+                    .flat_map(|(field, errors)| {
+                        errors.iter().map(move |error| match &error.message {
+                            Some(message) => message.to_string(),
+                            None => format!("{}格式错误", field),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                errors.sort();
+                R::fail(&errors.join("；"))
+            }
             AppError::Unknown(e) => {
                 tracing::error!("{:?}", e);
                 R::new(false,StatusCode::INTERNAL_SERVER_ERROR.as_u16() as u32, None, "服务器异常")
@@ -252,6 +279,13 @@ impl From<MultipartError> for AppError {
     }
 }
 
+/// 发生ValidationErrors错误时，通过?可以快速将[`ValidationErrors`]转换为[`AppError`]
+impl From<ValidationErrors> for AppError {
+    fn from(error: ValidationErrors) -> Self {
+        Self::ValidationError(error)
+    }
+}
+
 /// 发生anyhow::Error错误时，通过?可以快速将[`anyhow::Error`]转换为[`AppError`]
 impl From<anyhow::Error> for AppError {
     fn from(error: anyhow::Error) -> Self {
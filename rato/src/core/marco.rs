@@ -3,7 +3,7 @@
 macro_rules! require_all_perm {
     ($($perm:expr),+) => {{
         use $crate::entity::menu::RequirePermission;
-        use $crate::middleware::common::AuthState;
+        use $crate::middleware::AuthState;
         use $crate::require_auth;
         let state = AuthState::perm(RequirePermission::all(vec![$($perm),*]));
         require_auth![state]
@@ -22,26 +22,26 @@ macro_rules! require_any_perm {
     }};
 }
 
-/// 多角色校验中间件宏。提供角色类型[`crate::entity::role::RoleType`]，如果都满足，则通过；反之抛出异常
+/// 多角色校验中间件宏。提供角色值（`t_role.value`）或角色类型[`crate::entity::role::RoleType`]，如果都满足，则通过；反之抛出异常
 #[macro_export]
 macro_rules! require_all_role {
     ($($role:expr),+) => {{
         use $crate::entity::role::RequireRole;
-        use $crate::middleware::{AuthState,CheckType};
+        use $crate::middleware::AuthState;
         use $crate::require_auth;
-        let state = AuthState::role(RequireRole::all(vec![$($role),*]))
+        let state = AuthState::role(RequireRole::all(vec![$($role.to_string()),*]));
         require_auth![state]
     }};
 }
 
-/// 任一角色校验中间件宏。提供角色类型[`crate::entity::role::RoleType`]，如果有一个满足，则通过；反之抛出异常
+/// 任一角色校验中间件宏。提供角色值（`t_role.value`）或角色类型[`crate::entity::role::RoleType`]，如果有一个满足，则通过；反之抛出异常
 #[macro_export]
 macro_rules! require_any_role {
     ($($role:expr),+) => {{
         use $crate::entity::role::RequireRole;
-        use $crate::middleware::{AuthState,CheckType};
+        use $crate::middleware::AuthState;
         use $crate::require_auth;
-        let state = AuthState::role(RequireRole::any(vec![$($role),*]))
+        let state = AuthState::role(RequireRole::any(vec![$($role.to_string()),*]));
         require_auth![state]
     }};
 }
@@ -60,7 +60,7 @@ macro_rules! require_auth {
 macro_rules! require_token {
     () => {{
         use rato_core::authenticator::AuthenticatorLayer;
-        use crate::middleware::AuthState;
+        use $crate::middleware::AuthState;
         AuthenticatorLayer::new(AuthState::default())
     }};
 }
@@ -70,11 +70,20 @@ macro_rules! require_token {
 macro_rules! global_error_handler {
     () => {{
         use rato_core::error_handler::GlobalErrorHandlerLayer;
-        use crate::middleware::AuthState;
+        use $crate::middleware::AuthState;
         GlobalErrorHandlerLayer::new(AuthState::default())
     }};
 }
 
+/// 操作日志中间件宏，记录全部POST请求
+#[macro_export]
+macro_rules! oper_log {
+    ($app_state:expr) => {{
+        use $crate::utils::oper_log::OperLogStore;
+        OperLogStore::layer($app_state)
+    }};
+}
+
 /// to_redis_args宏
 #[macro_export]
 macro_rules! to_redis_args {
@@ -1,4 +1,5 @@
 pub mod error;
 pub mod result;
+pub mod page;
 pub mod constant;
 pub mod marco;
@@ -0,0 +1,231 @@
+use crate::core::error::AppError;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, Order, PaginatorTrait,
+    QueryFilter, QueryOrder, Select,
+};
+use serde::{Deserialize, Serialize};
+
+/// 单页最大条数
+const MAX_PAGE_SIZE: u64 = 100;
+
+/// 最大页码，避免计算偏移量时溢出
+const MAX_PAGE: u64 = 100_000;
+
+/// 分页结果
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    // 总条数
+    pub total: u64,
+    // 当前页，从1开始
+    pub page: u64,
+    pub size: u64,
+    pub records: Vec<T>,
+}
+
+/// 分页查询参数，与业务查询参数共用同一个查询字符串
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PageQuery {
+    // 当前页，从1开始
+    pub page: u64,
+    // 每页条数，最大100
+    pub size: u64,
+    // 排序字段，仅支持各列表接口白名单中的字段
+    pub sort: Option<String>,
+    // 排序方式。asc或desc（默认）
+    pub order: Option<String>,
+    // 文本条件是否模糊匹配
+    pub fuzzy: bool,
+    pub create_time_start: Option<DateTime<Utc>>,
+    pub create_time_end: Option<DateTime<Utc>>,
+}
+
+impl Default for PageQuery {
+    fn default() -> Self {
+        PageQuery {
+            page: 1,
+            size: 10,
+            sort: None,
+            order: None,
+            fuzzy: false,
+            create_time_start: None,
+            create_time_end: None,
+        }
+    }
+}
+
+impl PageQuery {
+    /// 分页查询，返回总条数及当前页数据
+    pub async fn fetch<E, C>(&self, select: Select<E>, db: &C) -> Result<Page<E::Model>, AppError>
+    where
+        E: EntityTrait,
+        E::Model: FromQueryResult + Sized + Send + Sync,
+        C: ConnectionTrait,
+    {
+        let (page, size) = self.bounds();
+        let paginator = select.paginate(db, size);
+        let total = paginator.num_items().await?;
+        let records = paginator.fetch_page(page - 1).await?;
+        Ok(Page {
+            total,
+            page,
+            size,
+            records,
+        })
+    }
+
+    /// 限定范围后的页码及每页条数
+    fn bounds(&self) -> (u64, u64) {
+        (self.page.clamp(1, MAX_PAGE), self.size.clamp(1, MAX_PAGE_SIZE))
+    }
+}
+
+/// 列表查询条件扩展
+pub trait PageSelect: Sized {
+    /// 文本条件。开启模糊匹配时使用like，否则精确匹配
+    fn filter_text<C: ColumnTrait>(self, column: C, value: Option<String>, fuzzy: bool) -> Self;
+
+    /// 创建时间范围条件，包含起止时间
+    fn filter_create_time<C: ColumnTrait>(self, column: C, query: &PageQuery) -> Self;
+
+    /// 按白名单中的字段排序，未指定排序字段时使用默认字段
+    fn sort_by<C: ColumnTrait>(
+        self,
+        query: &PageQuery,
+        columns: &[(&str, C)],
+        default: C,
+    ) -> Result<Self, AppError>;
+}
+
+impl<E: EntityTrait> PageSelect for Select<E> {
+    fn filter_text<C: ColumnTrait>(self, column: C, value: Option<String>, fuzzy: bool) -> Self {
+        match value.filter(|value| !value.is_empty()) {
+            None => self,
+            Some(value) if fuzzy => {
+                // 转义通配符，避免用户输入被当作匹配规则
+                let value = value
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                self.filter(column.like(format!("%{}%", value)))
+            }
+            Some(value) => self.filter(column.eq(value)),
+        }
+    }
+
+    fn filter_create_time<C: ColumnTrait>(self, column: C, query: &PageQuery) -> Self {
+        let mut select = self;
+        if let Some(start) = query.create_time_start {
+            select = select.filter(column.gte(start));
+        }
+        if let Some(end) = query.create_time_end {
+            select = select.filter(column.lte(end));
+        }
+        select
+    }
+
+    fn sort_by<C: ColumnTrait>(
+        self,
+        query: &PageQuery,
+        columns: &[(&str, C)],
+        default: C,
+    ) -> Result<Self, AppError> {
+        let column = match query.sort.as_deref().filter(|sort| !sort.is_empty()) {
+            None => default,
+            Some(sort) => columns
+                .iter()
+                .find(|(name, _)| *name == sort)
+                .map(|(_, column)| *column)
+                .ok_or(AppError::Other("不支持的排序字段"))?,
+        };
+        let order = match query.order.as_deref() {
+            None | Some("desc") => Order::Desc,
+            Some("asc") => Order::Asc,
+            Some(_) => return Err(AppError::Other("排序方式只能为asc或desc")),
+        };
+        Ok(self.order_by(column, order))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::error::AppError;
+    use crate::core::page::{PageQuery, PageSelect};
+    use crate::entity::prelude::User;
+    use crate::entity::user;
+    use chrono::{TimeZone, Utc};
+    use sea_orm::{DbBackend, EntityTrait, QueryTrait, Select};
+
+    #[test]
+    fn bounds() {
+        let query = |page, size| PageQuery { page, size, ..Default::default() };
+        assert_eq!(query(0, 0).bounds(), (1, 1));
+        assert_eq!(query(3, 20).bounds(), (3, 20));
+        assert_eq!(query(u64::MAX, u64::MAX).bounds(), (100_000, 100));
+    }
+
+    fn sql(select: Select<User>) -> String {
+        select.build(DbBackend::MySql).to_string()
+    }
+
+    fn sort(sort: Option<&str>, order: Option<&str>) -> Result<String, &'static str> {
+        let query = PageQuery {
+            sort: sort.map(String::from),
+            order: order.map(String::from),
+            ..Default::default()
+        };
+        let columns = [("account", user::Column::Account), ("createTime", user::Column::CreateTime)];
+        User::find()
+            .sort_by(&query, &columns, user::Column::Uid)
+            .map(sql)
+            .map_err(|e| match e {
+                AppError::Other(msg) => msg,
+                _ => "",
+            })
+    }
+
+    #[test]
+    fn filter_text() {
+        let account = |value: Option<&str>, fuzzy| {
+            sql(User::find().filter_text(user::Column::Account, value.map(String::from), fuzzy))
+        };
+        assert!(!account(None, true).contains("WHERE"));
+        assert!(!account(Some(""), false).contains("WHERE"));
+        assert!(account(Some("admin"), false).ends_with("WHERE `t_user`.`account` = 'admin'"));
+        assert!(account(Some("admin"), true).ends_with("WHERE `t_user`.`account` LIKE '%admin%'"));
+        // 通配符按字面匹配
+        assert!(account(Some("a%_\\b"), true).ends_with(r"WHERE `t_user`.`account` LIKE '%a\\%\\_\\\\b%'"));
+    }
+
+    #[test]
+    fn filter_create_time() {
+        let create_time = |query: &PageQuery| sql(User::find().filter_create_time(user::Column::CreateTime, query));
+        assert!(!create_time(&PageQuery::default()).contains("WHERE"));
+        let query = PageQuery {
+            create_time_start: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            create_time_end: Some(Utc.with_ymd_and_hms(2024, 12, 31, 23, 59, 59).unwrap()),
+            ..Default::default()
+        };
+        assert!(create_time(&query).ends_with(
+            "WHERE `t_user`.`create_time` >= '2024-01-01 00:00:00.000000 +00:00' AND `t_user`.`create_time` <= '2024-12-31 23:59:59.000000 +00:00'"
+        ));
+        let query = PageQuery {
+            create_time_end: query.create_time_end,
+            ..Default::default()
+        };
+        assert!(create_time(&query).ends_with("WHERE `t_user`.`create_time` <= '2024-12-31 23:59:59.000000 +00:00'"));
+    }
+
+    #[test]
+    fn sort_by() {
+        assert!(sort(None, None).unwrap().ends_with("ORDER BY `t_user`.`uid` DESC"));
+        assert!(sort(Some(""), Some("asc")).unwrap().ends_with("ORDER BY `t_user`.`uid` ASC"));
+        assert!(sort(Some("account"), Some("asc")).unwrap().ends_with("ORDER BY `t_user`.`account` ASC"));
+        assert!(sort(Some("createTime"), Some("desc")).unwrap().ends_with("ORDER BY `t_user`.`create_time` DESC"));
+        // 白名单外的字段及排序方式
+        assert_eq!(sort(Some("password"), None), Err("不支持的排序字段"));
+        assert_eq!(sort(Some("account; DROP TABLE t_user"), None), Err("不支持的排序字段"));
+        assert_eq!(sort(Some("account"), Some("random")), Err("排序方式只能为asc或desc"));
+    }
+}
@@ -1,12 +1,15 @@
 use crate::core::error::AppError;
-use axum::extract::{FromRequest, FromRequestParts};
+use crate::state::AppState;
+use axum::extract::{ConnectInfo, FromRequest, FromRequestParts};
 use axum::http::request::Parts;
-use axum::http::{StatusCode, Uri};
+use axum::http::{Extensions, HeaderMap, StatusCode, Uri};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::{Debug, Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::Arc;
 
 /// 响应结果
 #[derive(Debug, Clone, Serialize)]
@@ -56,9 +59,12 @@ where
     }
 }
 
-impl<T> Display for R<T> {
+impl<T> Display for R<T>
+where
+    T: Serialize,
+{
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", Json(self).to_string())
+        write!(f, "{}", serde_json::to_string(self).map_err(|_| std::fmt::Error)?)
     }
 }
 
@@ -82,13 +88,13 @@ where
         let query = raw_query
             .split("&")
             .filter(|q| {
-                let l = (&q).split("=").last().clone();
+                let l = q.split("=").last();
                 l.is_some() && l.unwrap() != ""
             })
             .collect::<Vec<_>>()
             .join("&");
         let deserializer =
-            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_str().as_bytes()));
+            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
         let params = serde_path_to_error::deserialize(deserializer)
             .map_err(|e| {
                 tracing::error!("{}", e);
@@ -110,3 +116,80 @@ where
         Self::try_from_uri(&parts.uri)
     }
 }
+
+/// 客户端信息解析器。连接地址为信任的代理时ip取代理转发请求头，否则取连接地址
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: String,
+    pub user_agent: String,
+    pub device: String,
+}
+
+impl ClientInfo {
+    /// 根据请求头解析客户端信息
+    pub fn from_parts(parts: &Parts) -> Self {
+        Self::resolve(&parts.headers, &parts.extensions)
+    }
+
+    /// 根据请求头及请求扩展解析客户端信息
+    pub fn resolve(headers: &HeaderMap, extensions: &Extensions) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let peer = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        // 未配置信任的代理时只使用连接地址
+        let ip = match extensions.get::<Arc<AppState>>() {
+            Some(app_state) => app_state.trusted_proxies.client_ip(
+                peer,
+                header("X-Forwarded-For").as_deref(),
+                header("X-Real-IP").as_deref(),
+            ),
+            None => peer,
+        }
+        .map(|ip| ip.to_string())
+        .unwrap_or_default();
+        let user_agent = header("User-Agent").unwrap_or_default();
+        // 客户端未指定设备名称时，根据User-Agent粗略识别
+        let device = header("X-Device").unwrap_or_else(|| Self::device(&user_agent).to_string());
+        ClientInfo {
+            ip,
+            user_agent,
+            device,
+        }
+    }
+
+    fn device(user_agent: &str) -> &'static str {
+        let ua = user_agent.to_lowercase();
+        if ua.contains("android") {
+            "Android"
+        } else if ua.contains("iphone") || ua.contains("ipad") {
+            "iOS"
+        } else if ua.contains("windows") {
+            "Windows"
+        } else if ua.contains("mac os") {
+            "macOS"
+        } else if ua.contains("linux") {
+            "Linux"
+        } else {
+            "Unknown"
+        }
+    }
+}
+
+/// 自定义客户端信息解析实现
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self::from_parts(parts))
+    }
+}
@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+/// 图形验证码
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptchaVo {
+    // 验证码id，登录及注册时随验证码一并提交
+    pub captcha_id: String,
+    // base64编码的png图片，data url格式
+    pub image: String,
+    // 有效期，单位秒
+    pub expires_in: u64,
+}
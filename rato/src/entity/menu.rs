@@ -2,17 +2,35 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use crate::middleware::CheckType;
+use crate::utils::scope::CreatorColumn;
+use crate::utils::tenant::TenantEntity;
+use crate::utils::tree::TreeNode;
 
 #[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "t_menu")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub uid: i64,
-    #[sea_orm(unique)]
+    // 所属租户
+    #[serde(skip)]
+    pub tenant_id: i64,
+    // 租户内唯一
     pub value: String,
     pub name: String,
     pub r#type: String,
+    // 父菜单id，0为根菜单
+    pub parent_id: i64,
+    // 同级排序，升序
+    pub sort: i32,
+    // 前端路由地址
+    pub path: Option<String>,
+    // 前端组件路径
+    pub component: Option<String>,
+    pub icon: Option<String>,
+    // 是否在导航中显示
+    pub visible: bool,
     pub creator_id: i64,
     pub create_time: DateTimeUtc,
     pub updater_id: Option<i64>,
@@ -36,6 +54,18 @@ impl Related<super::role_menu::Entity> for Entity {
 }
 impl ActiveModelBehavior for ActiveModel {}
 
+impl TenantEntity for Entity {
+    fn tenant_column() -> Self::Column {
+        Column::TenantId
+    }
+}
+
+impl CreatorColumn for Entity {
+    fn creator_column() -> Self::Column {
+        Column::CreatorId
+    }
+}
+
 
 #[derive(Debug, Clone, Deserialize, Default)]
 #[serde(default)]
@@ -43,6 +73,62 @@ pub struct MenuBody {
     pub uid: i64,
     pub name: String,
     pub value: String,
+    // 类型。menu（默认）或button
+    pub r#type: String,
+    pub parent_id: i64,
+    pub sort: i32,
+    pub path: Option<String>,
+    pub component: Option<String>,
+    pub icon: Option<String>,
+    // 未指定时显示
+    pub visible: Option<bool>,
+}
+
+/// 菜单类型
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MenuType {
+    Menu,
+    Button,
+}
+
+impl MenuType {
+    pub fn from_str(str: &str) -> Option<MenuType> {
+        match str {
+            "menu" => Some(MenuType::Menu),
+            "button" => Some(MenuType::Button),
+            _ => None,
+        }
+    }
+}
+
+impl Display for MenuType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            MenuType::Menu => "menu",
+            MenuType::Button => "button",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// 前端路由
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteVo {
+    pub name: String,
+    pub path: String,
+    pub component: Option<String>,
+    pub meta: RouteMeta,
+    pub children: Vec<RouteVo>,
+}
+
+/// 前端路由元信息
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteMeta {
+    pub title: String,
+    pub icon: Option<String>,
+    pub hidden: bool,
+    // 菜单权限值
+    pub perm: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -53,6 +139,10 @@ pub struct MenuQuery {
     pub value: Option<String>,
 }
 
+/// 登录用户均拥有的基础权限，即获取当前用户信息、前端路由、校验令牌及退出登录所需的权限。
+/// 可通过`basic_perms_enabled`关闭，仍可被显式拒绝
+pub const BASIC_PERMISSIONS: [&str; 4] = ["user:me", "user:routes", "token:check", "token:logout"];
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RequirePermission {
     pub perms: Vec<String>,
@@ -83,9 +173,28 @@ impl RequirePermission {
     }
 }
 
+#[allow(unused)]
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum PermissionType {
     Token(String),
     User(String),
     Role(String),
 }
+
+impl From<TreeNode<Model>> for RouteVo {
+    fn from(tree: TreeNode<Model>) -> Self {
+        let menu = tree.node;
+        RouteVo {
+            name: menu.value.clone(),
+            path: menu.path.unwrap_or_default(),
+            component: menu.component,
+            meta: RouteMeta {
+                title: menu.name,
+                icon: menu.icon,
+                hidden: !menu.visible,
+                perm: menu.value,
+            },
+            children: tree.children.into_iter().map(RouteVo::from).collect(),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::to_redis_args;
+use redis::RedisWrite;
+use redis::ToRedisArgs;
+use serde::{Deserialize, Serialize};
+
+/// 绑定两步验证时返回的密钥信息
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaEnrollVo {
+    // base32编码的密钥，供无法扫码时手动输入
+    pub secret: String,
+    // otpauth://格式的密钥地址
+    pub uri: String,
+    // 密钥地址的二维码，base64编码的png图片，data url格式
+    pub qr_code: String,
+}
+
+/// 账号密码校验通过后，需两步验证时返回的临时令牌
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaChallenge {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    // 有效期，单位秒
+    pub expires_in: i64,
+}
+
+/// 待两步验证的登录，以临时令牌哈希为key保存在缓存中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaPending {
+    pub uid: i64,
+    pub account: String,
+}
+
+to_redis_args!(MfaPending);
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct MfaCodeBody {
+    // 6位动态验证码
+    pub code: String,
+}
+
+/// 两步验证登录，code为动态验证码或恢复码
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct MfaLoginBody {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct MfaResetBody {
+    pub user_uid: i64,
+}
@@ -2,8 +2,21 @@
 
 pub mod prelude;
 
+pub mod captcha;
+pub mod dept;
+pub mod file;
+pub mod login_log;
 pub mod menu;
+pub mod mfa;
+pub mod mfa_recovery_code;
+pub mod oper_log;
 pub mod role;
+pub mod role_dept;
 pub mod role_menu;
+pub mod role_parent;
+pub mod session;
+pub mod tenant;
+pub mod token;
 pub mod user;
 pub mod user_role;
+pub mod user_status_log;
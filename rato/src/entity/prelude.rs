@@ -1,7 +1,16 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.8
 
+pub use super::dept::Entity as Dept;
+pub use super::file::Entity as File;
+pub use super::login_log::Entity as LoginLog;
 pub use super::menu::Entity as Menu;
+pub use super::mfa_recovery_code::Entity as MfaRecoveryCode;
+pub use super::oper_log::Entity as OperLog;
 pub use super::role::Entity as Role;
+pub use super::role_dept::Entity as RoleDept;
 pub use super::role_menu::Entity as RoleMenu;
+pub use super::role_parent::Entity as RoleParent;
+pub use super::tenant::Entity as Tenant;
 pub use super::user::Entity as User;
 pub use super::user_role::Entity as UserRole;
+pub use super::user_status_log::Entity as UserStatusLog;
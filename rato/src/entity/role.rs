@@ -4,15 +4,22 @@ use std::fmt::Display;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use crate::middleware::CheckType;
+use crate::utils::scope::CreatorColumn;
+use crate::utils::tenant::TenantEntity;
 
 #[derive(Clone, Debug, PartialEq, Serialize,DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "t_role")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub uid: i64,
-    #[sea_orm(unique)]
+    // 所属租户
+    #[serde(skip)]
+    pub tenant_id: i64,
+    // 租户内唯一
     pub value: String,
     pub name: String,
+    // 数据范围。all：全部，self：本人，dept：本部门，custom：指定部门
+    pub data_scope: String,
     pub creator_id: i64,
     pub create_time: DateTimeUtc,
     pub updater_id: Option<i64>,
@@ -52,6 +59,18 @@ impl Related<super::menu::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+impl TenantEntity for Entity {
+    fn tenant_column() -> Self::Column {
+        Column::TenantId
+    }
+}
+
+impl CreatorColumn for Entity {
+    fn creator_column() -> Self::Column {
+        Column::CreatorId
+    }
+}
+
 
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -78,6 +97,46 @@ pub struct AuthPermBody {
     pub perm_uids: Vec<i64>,
 }
 
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct DataScopeBody {
+    pub role_id: i64,
+    // all、self、dept或custom
+    pub data_scope: String,
+    // 数据范围为custom时可见的部门
+    pub dept_uids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct RoleParentBody {
+    pub role_id: i64,
+    // 父角色，角色继承父角色的全部权限
+    pub parent_uids: Vec<i64>,
+}
+
+/// 角色的有效权限
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleEffectiveVo {
+    // 角色自身及继承的全部角色
+    pub roles: Vec<Model>,
+    pub perms: Vec<EffectivePermVo>,
+}
+
+/// 有效权限及其来源角色
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectivePermVo {
+    pub menu_id: i64,
+    pub value: String,
+    pub name: String,
+    // 是否为继承的权限
+    pub inherited: bool,
+    // 授予该权限的角色值
+    pub sources: Vec<String>,
+}
+
+/// 内置角色类型，仅作为类型化的便捷写法，角色校验以`t_role.value`为准
+#[allow(unused)]
 #[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone)]
 pub enum RoleType {
     Admin,
@@ -85,6 +144,7 @@ pub enum RoleType {
     Api,
 }
 
+#[allow(unused)]
 impl RoleType {
     pub fn from_str(str: &str) -> Option<RoleType> {
         match str {
@@ -107,29 +167,27 @@ impl Display for RoleType {
     }
 }
 
+/// 角色要求，角色值对应`t_role.value`
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct RequireRole {
-    pub roles: Vec<RoleType>,
+    pub roles: Vec<String>,
     pub check_type: CheckType,
 }
 
 #[allow(unused)]
 impl RequireRole {
-    pub fn new(roles: Vec<RoleType>, check_type: CheckType) -> RequireRole {
-        RequireRole { roles, check_type }
-    }
-
-    pub fn any(roles: Vec<RoleType>) -> RequireRole {
+    pub fn new<R: ToString>(roles: Vec<R>, check_type: CheckType) -> RequireRole {
         RequireRole {
-            roles,
-            check_type: CheckType::Or,
+            roles: roles.iter().map(R::to_string).collect(),
+            check_type,
         }
     }
 
-    pub fn all(roles: Vec<RoleType>) -> RequireRole {
-        RequireRole {
-            roles,
-            check_type: CheckType::And,
-        }
+    pub fn any<R: ToString>(roles: Vec<R>) -> RequireRole {
+        Self::new(roles, CheckType::Or)
+    }
+
+    pub fn all<R: ToString>(roles: Vec<R>) -> RequireRole {
+        Self::new(roles, CheckType::And)
     }
 }
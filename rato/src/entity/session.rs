@@ -0,0 +1,59 @@
+use crate::entity::user::LoginUser;
+use crate::to_redis_args;
+use chrono::{DateTime, Utc};
+use redis::RedisWrite;
+use redis::ToRedisArgs;
+use serde::{Deserialize, Serialize};
+
+/// 登录会话，以会话id为key保存在缓存中。每次登录（每台设备）对应一个会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    // 会话id，即访问令牌的jti，同时也是刷新令牌族id
+    pub sid: String,
+    pub uid: i64,
+    // 设备
+    pub device: String,
+    // 登录ip
+    pub ip: String,
+    pub user_agent: String,
+    pub login_time: DateTime<Utc>,
+    // 最后活跃时间
+    pub last_seen: DateTime<Utc>,
+    // 登录用户，刷新令牌时用于签发新的访问令牌
+    pub login_user: LoginUser,
+}
+
+to_redis_args!(SessionInfo);
+
+/// 会话列表返回
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionVo {
+    pub sid: String,
+    pub device: String,
+    pub ip: String,
+    pub user_agent: String,
+    pub login_time: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    // 是否为当前请求所属会话
+    pub current: bool,
+}
+
+impl SessionVo {
+    pub fn new(session: SessionInfo, current_sid: &str) -> Self {
+        SessionVo {
+            current: session.sid == current_sid,
+            sid: session.sid,
+            device: session.device,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            login_time: session.login_time,
+            last_seen: session.last_seen,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct SessionBody {
+    pub sid: String,
+}
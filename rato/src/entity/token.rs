@@ -0,0 +1,34 @@
+use crate::to_redis_args;
+use redis::RedisWrite;
+use redis::ToRedisArgs;
+use serde::{Deserialize, Serialize};
+
+/// 登录及刷新后返回的令牌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    // 访问令牌有效期，单位秒
+    pub expires_in: i64,
+    // 刷新令牌有效期，单位秒
+    pub refresh_expires_in: i64,
+}
+
+/// 刷新令牌记录，以令牌哈希为key保存在缓存中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshRecord {
+    pub uid: i64,
+    // 令牌族id，与访问令牌的jti一致
+    pub family: String,
+    // 已轮换的旧令牌，再次使用即视为泄露
+    pub used: bool,
+}
+
+to_redis_args!(RefreshRecord);
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct RefreshBody {
+    pub refresh_token: String,
+}
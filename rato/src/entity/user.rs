@@ -2,27 +2,53 @@
 
 use crate::core::error::AppError;
 use crate::to_redis_args;
-use chrono::Utc;
+use crate::utils::auth::PermMatcher;
+use crate::utils::scope::{CreatorColumn, DataScope};
+use crate::utils::tenant::TenantEntity;
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
 use derive_builder::Builder;
 use redis::RedisWrite;
 use redis::ToRedisArgs;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use validator::{Validate, ValidationError};
 
 #[derive(Clone, Debug, PartialEq, Serialize, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "t_user")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub uid: i64,
-    #[sea_orm(unique)]
+    // 所属租户
+    #[serde(skip)]
+    pub tenant_id: i64,
+    // 租户内唯一
     pub account: String,
     pub name: String,
+    // 密码哈希不返回给客户端
+    #[serde(skip_serializing)]
     pub password: String,
     pub creator_id: i64,
     pub create_time: DateTimeUtc,
     pub updater_id: Option<i64>,
     pub update_time: Option<DateTimeUtc>,
     pub avatar: Option<String>,
+    // 所属部门
+    pub dept_id: Option<i64>,
+    // 账号状态。active：正常，disabled：停用
+    pub status: String,
+    // 锁定截止时间，之前不允许登录
+    pub locked_until: Option<DateTimeUtc>,
+    // 账号过期时间，之后不允许登录
+    pub expire_time: Option<DateTimeUtc>,
+    // 是否启用两步验证
+    pub mfa_enabled: bool,
+    // 两步验证密钥，base32编码，不返回给客户端
+    #[serde(skip_serializing)]
+    pub mfa_secret: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -43,6 +69,25 @@ impl Related<super::role::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+impl Model {
+    /// 账号在指定时间不可用的原因，可用时返回None
+    pub fn inactive_reason(&self, now: DateTime<Utc>) -> Option<&'static str> {
+        AccountState::from(self).inactive_reason(now)
+    }
+}
+
+impl TenantEntity for Entity {
+    fn tenant_column() -> Self::Column {
+        Column::TenantId
+    }
+}
+
+impl CreatorColumn for Entity {
+    fn creator_column() -> Self::Column {
+        Column::CreatorId
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Builder, Default)]
 #[builder(default, build_fn(error = "AppError"))]
 pub struct LoginUser {
@@ -57,10 +102,29 @@ pub struct LoginUser {
     pub perms: Option<Vec<String>>,
     pub roles: Option<Vec<String>>,
     pub token: Option<String>,
+    // 授权时预编译的权限匹配器，不序列化
+    #[serde(skip)]
+    pub matcher: Option<Arc<PermMatcher>>,
 }
 
 to_redis_args!(LoginUser);
 
+/// 用户角色及权限缓存，版本号与全局权限版本号不一致时重新加载
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct UserPerm {
+    pub version: i64,
+    pub roles: Vec<String>,
+    pub perms: Vec<String>,
+    #[serde(default)]
+    pub data_scope: DataScope,
+    // 租户菜单中声明的全部权限值，较短的权限仅包含其中的下级权限
+    #[serde(default)]
+    pub declared: Vec<String>,
+}
+
+to_redis_args!(UserPerm);
+
+#[allow(unused)]
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct LoginReq {
@@ -75,6 +139,41 @@ pub struct UserBody {
     pub account: String,
     pub password: String,
     pub name: String,
+    // 登录及注册时的验证码id
+    pub captcha_id: Option<String>,
+    // 登录及注册时的验证码
+    pub captcha: Option<String>,
+}
+
+/// 修改本人密码
+#[derive(Debug, Clone, Deserialize, Default, Validate)]
+#[serde(default)]
+pub struct PasswordBody {
+    pub old_password: String,
+    #[validate(length(min = 6, max = 64, message = "新密码长度为6到64位"))]
+    pub new_password: String,
+}
+
+/// 修改本人资料，联系方式为空时清除
+#[derive(Debug, Clone, Deserialize, Default, Validate)]
+#[serde(default)]
+pub struct ProfileBody {
+    #[validate(length(min = 1, max = 64, message = "用户名长度为1到64位"))]
+    pub name: String,
+    #[validate(custom(function = "validate_phone", message = "手机号格式错误"))]
+    pub phone: Option<String>,
+    #[validate(email(message = "邮箱格式错误"), length(max = 128, message = "邮箱长度不能超过128位"))]
+    pub email: Option<String>,
+}
+
+/// 手机号可带国际区号，仅允许数字、空格、`+`及`-`
+fn validate_phone(phone: &str) -> Result<(), ValidationError> {
+    let digits = phone.chars().filter(|ch| ch.is_ascii_digit()).count();
+    let valid = phone.trim_start_matches('+').chars().all(|ch| ch.is_ascii_digit() || ch == ' ' || ch == '-');
+    if !valid || !(5..=20).contains(&digits) {
+        return Err(ValidationError::new("phone"));
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -84,6 +183,7 @@ pub struct UserQuery {
     pub account: Option<String>,
     pub password: Option<String>,
     pub name: Option<String>,
+    pub dept_id: Option<i64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -92,3 +192,86 @@ pub struct AuthRoleBody {
     pub user_id: i64,
     pub role_uids: Vec<i64>,
 }
+
+/// 账号状态
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum UserStatus {
+    Active,
+    Disabled,
+}
+
+impl UserStatus {
+    pub fn from_str(str: &str) -> Option<UserStatus> {
+        match str {
+            "active" => Some(UserStatus::Active),
+            "disabled" => Some(UserStatus::Disabled),
+            _ => None,
+        }
+    }
+}
+
+impl Display for UserStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            UserStatus::Active => "active",
+            UserStatus::Disabled => "disabled",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// 账号状态快照，缓存后由认证中间件逐请求校验
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountState {
+    pub status: String,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub expire_time: Option<DateTime<Utc>>,
+}
+
+to_redis_args!(AccountState);
+
+impl AccountState {
+    /// 账号在指定时间不可用的原因，可用时返回None
+    pub fn inactive_reason(&self, now: DateTime<Utc>) -> Option<&'static str> {
+        if UserStatus::from_str(&self.status) != Some(UserStatus::Active) {
+            return Some("账号已停用");
+        }
+        if self.locked_until.is_some_and(|locked_until| locked_until > now) {
+            return Some("账号已锁定");
+        }
+        if self.expire_time.is_some_and(|expire_time| expire_time <= now) {
+            return Some("账号已过期");
+        }
+        None
+    }
+}
+
+impl From<&Model> for AccountState {
+    fn from(user: &Model) -> Self {
+        AccountState {
+            status: user.status.clone(),
+            locked_until: user.locked_until,
+            expire_time: user.expire_time,
+        }
+    }
+}
+
+/// 修改账号状态，锁定截止时间及过期时间为空时清除
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct UserStatusBody {
+    pub user_id: i64,
+    // active或disabled
+    pub status: String,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub expire_time: Option<DateTime<Utc>>,
+    // 变更原因
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct UserStatusLogQuery {
+    pub user_id: Option<i64>,
+    pub operator_id: Option<i64>,
+}
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.8
 
+use crate::utils::tenant::TenantEntity;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -9,6 +10,7 @@ pub struct Model {
     pub user_id: i64,
     #[sea_orm(primary_key)]
     pub role_id: i64,
+    pub tenant_id: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -27,3 +29,9 @@ pub enum Relation {
     Role,
 }
 impl ActiveModelBehavior for ActiveModel {}
+
+impl TenantEntity for Entity {
+    fn tenant_column() -> Self::Column {
+        Column::TenantId
+    }
+}
@@ -0,0 +1,20 @@
+use crate::core::error::AppError;
+use crate::core::result::R;
+use crate::state::AppState;
+use crate::utils::captcha::CaptchaUtils;
+use axum::response::IntoResponse;
+use axum::Extension;
+use std::sync::Arc;
+
+/// 验证码handler
+pub struct CaptchaHandler;
+
+#[allow(unused)]
+impl CaptchaHandler {
+    /// 生成图形验证码
+    pub async fn create(
+        Extension(app_state): Extension<Arc<AppState>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Ok(R::ok(CaptchaUtils::create(&app_state).await?))
+    }
+}
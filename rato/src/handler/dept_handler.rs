@@ -0,0 +1,286 @@
+use crate::core::error::AppError;
+use crate::core::page::{PageQuery, PageSelect};
+use crate::core::result::{AppJson, AppQuery, R};
+use crate::entity::dept::{DeptBody, DeptQuery, DeptUserBody};
+use crate::entity::prelude::{Dept, RoleDept, User};
+use crate::entity::{dept, role_dept, user};
+use crate::state::{AppState, RequestState};
+use crate::utils::tenant::TenantUtils;
+use crate::utils::perm::PermUtils;
+use crate::utils::scope::{DataScope, ScopeSelect};
+use crate::utils::tree::TreeUtils;
+use axum::response::IntoResponse;
+use axum::Extension;
+use chrono::Utc;
+use rato_core::database::DbPool;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, IntoActiveModel, ModelTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// 部门handler
+pub struct DeptHandler;
+
+#[allow(unused)]
+impl DeptHandler {
+    /// 添加部门
+    pub async fn add(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(dept): AppJson<DeptBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Self::check_leader(&app_state, &request_state.data_scope, dept.leader_id).await?;
+        let transaction = app_state.begin().await?;
+        Self::check_parent(&app_state, &transaction, None, dept.parent_id).await?;
+        let dept = dept::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
+            name: Set(dept.name),
+            parent_id: Set(dept.parent_id),
+            sort: Set(dept.sort),
+            leader_id: Set(dept.leader_id),
+            creator_id: Set(request_state.login_user.uid),
+            create_time: Set(Utc::now()),
+            ..Default::default()
+        };
+        let dept = dept.insert(&transaction).await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("添加部门失败")
+        })?;
+        transaction.commit().await?;
+        Ok(R::ok(dept))
+    }
+
+    /// 删除部门。存在下级部门或部门下仍有用户时不允许删除
+    pub async fn remove(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(dept): AppJson<DeptBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let dept = app_state.find_by_id::<Dept>(dept.uid)
+            .scoped(&request_state.data_scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or_else(|| AppError::Other("未找到部门信息"))?;
+        let children = app_state.find::<Dept>()
+            .filter(dept::Column::ParentId.eq(dept.uid))
+            .count(&app_state.db.connection)
+            .await?;
+        if children > 0 {
+            return Err(AppError::Other("存在下级部门，无法删除"));
+        }
+        let users = app_state.find::<User>()
+            .filter(user::Column::DeptId.eq(dept.uid))
+            .count(&app_state.db.connection)
+            .await?;
+        if users > 0 {
+            return Err(AppError::Other("部门下存在用户，无法删除"));
+        }
+        let transaction = app_state.begin().await?;
+        app_state.delete_many::<RoleDept>()
+            .filter(role_dept::Column::DeptId.eq(dept.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空部门数据范围关联失败")
+            })?;
+        dept.clone().delete(&transaction).await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("删除部门失败")
+        })?;
+        transaction.commit().await?;
+        // 角色的自定义数据范围可能包含该部门
+        PermUtils::invalidate(&app_state).await?;
+        Ok(R::ok(dept))
+    }
+
+    /// 编辑部门
+    pub async fn edit(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(update_dept): AppJson<DeptBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Self::check_leader(&app_state, &request_state.data_scope, update_dept.leader_id).await?;
+        let transaction = app_state.begin().await?;
+        // 上级部门校验与更新在同一事务中，避免并发移动部门形成环
+        Self::check_parent(&app_state, &transaction, Some(update_dept.uid), update_dept.parent_id).await?;
+        let mut dept = app_state.find_by_id::<Dept>(update_dept.uid)
+            .scoped(&request_state.data_scope)
+            .one(&transaction)
+            .await?
+            .ok_or_else(|| AppError::Other("未找到部门信息"))?;
+        dept.name = update_dept.name;
+        dept.parent_id = update_dept.parent_id;
+        dept.sort = update_dept.sort;
+        dept.leader_id = update_dept.leader_id;
+        dept.updater_id = Some(request_state.login_user.uid);
+        dept.update_time = Some(Utc::now());
+        dept.clone()
+            .into_active_model()
+            .reset_all()
+            .update(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("更新部门失败")
+            })?;
+        transaction.commit().await?;
+        Ok(R::ok(dept))
+    }
+
+    /// 部门树，同级按sort升序
+    pub async fn tree(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let depts = app_state.find::<Dept>()
+            .scoped(&request_state.data_scope)
+            .order_by_asc(dept::Column::Sort)
+            .order_by_asc(dept::Column::Uid)
+            .all(&app_state.db.connection)
+            .await?;
+        Ok(R::ok(TreeUtils::build(depts, |dept| dept.uid, |dept| dept.parent_id)))
+    }
+
+    /// 查询部门
+    pub async fn info(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(dept): AppQuery<DeptQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        if dept.uid.is_none() {
+            return Err(AppError::Other("uid不能为空"));
+        }
+        let dept = app_state.find_by_id::<Dept>(dept.uid.unwrap())
+            .scoped(&request_state.data_scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or_else(|| AppError::Other("未找到部门信息"))?;
+        Ok(R::ok(dept))
+    }
+
+    /// 分页查询部门
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(dept): AppQuery<DeptQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut select = app_state.find::<Dept>().scoped(&request_state.data_scope);
+        if let Some(uid) = dept.uid {
+            select = select.filter(dept::Column::Uid.eq(uid));
+        }
+        if let Some(parent_id) = dept.parent_id {
+            select = select.filter(dept::Column::ParentId.eq(parent_id));
+        }
+        let select = select
+            .filter_text(dept::Column::Name, dept.name, page.fuzzy)
+            .filter_create_time(dept::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[
+                    ("uid", dept::Column::Uid),
+                    ("name", dept::Column::Name),
+                    ("sort", dept::Column::Sort),
+                    ("create_time", dept::Column::CreateTime),
+                    ("update_time", dept::Column::UpdateTime),
+                ],
+                dept::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
+    /// 将用户分配到部门。部门及用户均需在调用者的数据范围内，任一用户不满足时全部不分配
+    pub async fn assign_user(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(dept_user): AppJson<DeptUserBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let scope = &request_state.data_scope;
+        let dept = app_state.find_by_id::<Dept>(dept_user.dept_id)
+            .scoped(scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or_else(|| AppError::Other("未找到部门信息"))?;
+        let user_uids = dept_user.user_uids.into_iter().collect::<HashSet<_>>();
+        if user_uids.is_empty() {
+            return Ok(R::ok(0));
+        }
+        let transaction = app_state.begin().await?;
+        let count = app_state.find::<User>()
+            .scoped(scope)
+            .filter(user::Column::Uid.is_in(user_uids.iter().copied()))
+            .lock_exclusive()
+            .count(&transaction)
+            .await?;
+        if count as usize != user_uids.len() {
+            return Err(AppError::Other("未找到用户信息"));
+        }
+        let result = app_state.update_many::<User>()
+            .col_expr(user::Column::DeptId, Expr::value(dept.uid))
+            .filter(user::Column::Uid.is_in(user_uids.iter().copied()))
+            .filter(scope.condition::<User>())
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("分配部门失败")
+            })?;
+        // 连接开启了FOUND_ROWS，影响行数为匹配的行数
+        if result.rows_affected as usize != user_uids.len() {
+            return Err(AppError::Other("分配部门失败"));
+        }
+        transaction.commit().await?;
+        // 本部门数据范围依赖用户所属部门
+        PermUtils::invalidate(&app_state).await?;
+        Ok(R::ok(result.rows_affected))
+    }
+
+    /// 校验上级部门存在，编辑时还需校验不会形成环。
+    /// 需在事务中调用，读取时锁定当前租户的部门，并发的修改在事务提交前等待
+    async fn check_parent<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+        uid: Option<i64>,
+        parent_id: i64,
+    ) -> Result<(), AppError> {
+        if parent_id == 0 {
+            return Ok(());
+        }
+        let parents = app_state.find::<Dept>()
+            .select_only()
+            .column(dept::Column::Uid)
+            .column(dept::Column::ParentId)
+            .lock_exclusive()
+            .into_tuple::<(i64, i64)>()
+            .all(connection)
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        if !parents.contains_key(&parent_id) {
+            return Err(AppError::Other("未找到上级部门信息"));
+        }
+        if let Some(uid) = uid {
+            if TreeUtils::has_cycle(&parents, &uid, &parent_id) {
+                return Err(AppError::Other("不能将部门移动到自身或其下级部门下"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验负责人用户存在且在调用者的数据范围内
+    async fn check_leader(app_state: &AppState, scope: &DataScope, leader_id: Option<i64>) -> Result<(), AppError> {
+        let Some(leader_id) = leader_id else {
+            return Ok(());
+        };
+        app_state.find_by_id::<User>(leader_id)
+            .scoped(scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到部门负责人信息"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::core::error::AppError;
+use crate::core::result::{AppJson, AppQuery, R};
+use crate::entity::file::{ChunkInitBody, FileBody, FileQuery};
+use crate::entity::prelude::File;
+use crate::state::{AppState, RequestState};
+use crate::utils::file::{ByteRange, FileUtils};
+use crate::utils::image::{ImageUtils, THUMBNAIL_SIZES};
+use crate::utils::scope::ScopeSelect;
+use crate::utils::upload::ChunkUtils;
+use axum::body::{Body, Bytes};
+use axum::extract::{Multipart, Path};
+use axum::http::header::{
+    ACCEPT_RANGES, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE,
+    CONTENT_TYPE, ETAG, RANGE, X_CONTENT_TYPE_OPTIONS,
+};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use sea_orm::QueryFilter;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+
+/// 文件handler
+pub struct FileHandler;
+
+#[allow(unused)]
+impl FileHandler {
+    /// 上传文件，multipart表单的file字段
+    pub async fn upload(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        mut multipart: Multipart,
+    ) -> Result<impl IntoResponse, AppError> {
+        let upload = FileUtils::upload(&mut multipart).await?;
+        let file = FileUtils::save(&app_state, request_state.login_user.uid, &upload).await?;
+        Ok(R::ok(file))
+    }
+
+    /// 下载文件，支持单个范围的Range请求。图片可通过size参数获取对应尺寸的缩略图
+    pub async fn download(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        Path(uid): Path<String>,
+        AppQuery(query): AppQuery<FileQuery>,
+        headers: HeaderMap,
+    ) -> Result<impl IntoResponse, AppError> {
+        let file = app_state
+            .find_by_id::<File>(uid)
+            .filter(FileUtils::downloadable(&request_state.data_scope))
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到文件信息"))?;
+        // 缩略图由服务端生成，原文件仅在文件头识别为图片时内联展示，其余一律作为附件下载
+        let (key, size, inline) = match query.size {
+            None => (file.sha256.clone(), file.size as u64, FileUtils::is_image(&app_state, &file).await),
+            Some(thumbnail) => {
+                let key = ImageUtils::thumbnail_key(&file.sha256, thumbnail);
+                if !THUMBNAIL_SIZES.contains(&thumbnail) || !file.mime.starts_with("image/") {
+                    return Err(AppError::Other("不支持的缩略图尺寸"));
+                }
+                // 缩略图仅在上传图片时生成，普通上传的图片没有缩略图
+                let size = app_state.storage.size(&key).await.map_err(|e| {
+                    tracing::warn!("{:?}", e);
+                    AppError::Other("未找到缩略图")
+                })?;
+                (key, size, true)
+            }
+        };
+        let range = headers.get(RANGE).and_then(|range| range.to_str().ok());
+        let (status, start, len) = match FileUtils::parse_range(range, size) {
+            ByteRange::Full => (StatusCode::OK, 0, size),
+            ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+            ByteRange::Unsatisfiable => {
+                let content_range = format!("bytes */{}", size);
+                return Ok((StatusCode::RANGE_NOT_SATISFIABLE, [(CONTENT_RANGE, content_range)]).into_response());
+            }
+        };
+        let reader = app_state.storage.read(&key, start, len).await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("读取文件失败")
+        })?;
+        let mut builder = Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, &file.mime)
+            .header(CONTENT_LENGTH, len)
+            .header(ACCEPT_RANGES, "bytes")
+            .header(ETAG, format!("\"{}\"", key))
+            .header(CACHE_CONTROL, "private, max-age=86400")
+            .header(X_CONTENT_TYPE_OPTIONS, "nosniff")
+            .header(CONTENT_DISPOSITION, FileUtils::content_disposition(&file.name, inline));
+        if status == StatusCode::PARTIAL_CONTENT {
+            builder = builder.header(CONTENT_RANGE, format!("bytes {}-{}/{}", start, start + len - 1, size));
+        }
+        builder
+            .body(Body::from_stream(ReaderStream::new(reader)))
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("读取文件失败")
+            })
+    }
+
+    /// 删除文件
+    pub async fn remove(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<FileBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let file = app_state
+            .find_by_id::<File>(body.uid)
+            .scoped(&request_state.data_scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到文件信息"))?;
+        FileUtils::remove(&app_state, file.clone()).await?;
+        Ok(R::ok(file))
+    }
+
+    /// 创建分片上传任务
+    pub async fn chunk_init(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<ChunkInitBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let upload = ChunkUtils::init(&app_state, request_state.login_user.uid, body).await?;
+        Ok(R::ok(upload))
+    }
+
+    /// 上传一个分片，请求体为分片内容
+    pub async fn chunk_put(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        Path((upload_id, index)): Path<(String, u32)>,
+        bytes: Bytes,
+    ) -> Result<impl IntoResponse, AppError> {
+        let upload = ChunkUtils::put(&app_state, request_state.login_user.uid, &upload_id, index, &bytes).await?;
+        Ok(R::ok(upload))
+    }
+
+    /// 查询分片上传任务状态，用于断点续传
+    pub async fn chunk_status(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        Path(upload_id): Path<String>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let upload = ChunkUtils::status(&app_state, request_state.login_user.uid, &upload_id).await?;
+        Ok(R::ok(upload))
+    }
+
+    /// 完成分片上传，合并分片并校验sha256
+    pub async fn chunk_complete(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        Path(upload_id): Path<String>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let file = ChunkUtils::complete(&app_state, request_state.login_user.uid, &upload_id).await?;
+        tracing::info!(target: "audit", "用户{}完成分片上传文件{}", request_state.login_user.uid, file.uid);
+        Ok(R::ok(file))
+    }
+}
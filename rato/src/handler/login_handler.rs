@@ -0,0 +1,74 @@
+use crate::core::error::AppError;
+use crate::core::page::{PageQuery, PageSelect};
+use crate::core::result::{AppJson, AppQuery, R};
+use crate::entity::login_log;
+use crate::entity::login_log::{LoginLogQuery, LoginUnlockBody};
+use crate::entity::prelude::LoginLog;
+use crate::state::{AppState, RequestState};
+use crate::utils::login::LoginGuard;
+use axum::response::IntoResponse;
+use axum::Extension;
+use sea_orm::{ColumnTrait, QueryFilter};
+use std::sync::Arc;
+
+/// 登录日志handler
+pub struct LoginHandler;
+
+#[allow(unused)]
+impl LoginHandler {
+    /// 分页查询登录日志
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(_request_state): Extension<Arc<RequestState>>,
+        AppQuery(log): AppQuery<LoginLogQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut select = app_state.find::<LoginLog>();
+        if let Some(user_id) = log.user_id {
+            select = select.filter(login_log::Column::UserId.eq(user_id));
+        }
+        if let Some(ip) = log.ip {
+            select = select.filter(login_log::Column::Ip.eq(ip));
+        }
+        if let Some(success) = log.success {
+            select = select.filter(login_log::Column::Success.eq(success));
+        }
+        let select = select
+            .filter_text(login_log::Column::Account, log.account, page.fuzzy)
+            .filter_create_time(login_log::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[
+                    ("uid", login_log::Column::Uid),
+                    ("account", login_log::Column::Account),
+                    ("create_time", login_log::Column::CreateTime),
+                ],
+                login_log::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
+    /// 解除账号或ip的登录锁定
+    pub async fn unlock(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<LoginUnlockBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut subjects = Vec::new();
+        if let Some(account) = body.account.filter(|account| !account.is_empty()) {
+            subjects.push(LoginGuard::account_subject(&account));
+        }
+        if let Some(ip) = body.ip.filter(|ip| !ip.is_empty()) {
+            subjects.push(LoginGuard::ip_subject(&ip));
+        }
+        if subjects.is_empty() {
+            return Err(AppError::Other("账号及ip不能同时为空"));
+        }
+        let mut unlocked = true;
+        for subject in &subjects {
+            unlocked &= LoginGuard::unlock(&app_state, subject).await;
+        }
+        tracing::info!(target: "audit", "解除登录锁定：{:?}，操作人：{}", subjects, request_state.login_user.uid);
+        Ok(R::ok(unlocked))
+    }
+}
@@ -1,14 +1,23 @@
 use crate::core::error::AppError;
+use crate::core::page::{PageQuery, PageSelect};
 use crate::core::result::{AppJson, AppQuery, R};
-use crate::entity::menu;
-use crate::entity::menu::{MenuBody, MenuQuery};
-use crate::entity::prelude::{Menu, Role};
+use crate::entity::menu::{MenuBody, MenuQuery, MenuType};
+use crate::entity::prelude::{Menu, RoleMenu};
+use crate::entity::{menu, role_menu};
 use crate::state::{AppState, RequestState};
+use crate::utils::tenant::TenantUtils;
+use crate::utils::perm::PermUtils;
+use crate::utils::scope::ScopeSelect;
+use crate::utils::tree::TreeUtils;
 use axum::response::IntoResponse;
 use axum::Extension;
 use chrono::Utc;
 use rato_core::database::DbPool;
-use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, ModelTrait, Set, TryIntoModel};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, IntoActiveModel, ModelTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// 菜单handler
@@ -22,42 +31,67 @@ impl MenuHandler {
         Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(menu): AppJson<MenuBody>,
     ) -> Result<impl IntoResponse, AppError> {
+        let menu_type = Self::menu_type(&menu.r#type)?;
         let transaction = app_state.begin().await?;
+        Self::check_parent(&app_state, &transaction, None, menu.parent_id).await?;
         let menu = menu::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
             name: Set(menu.name),
             value: Set(menu.value),
+            r#type: Set(menu_type.to_string()),
+            parent_id: Set(menu.parent_id),
+            sort: Set(menu.sort),
+            path: Set(menu.path),
+            component: Set(menu.component),
+            icon: Set(menu.icon),
+            visible: Set(menu.visible.unwrap_or(true)),
             creator_id: Set(request_state.login_user.uid),
             create_time: Set(Utc::now()),
             ..Default::default()
         };
-        Menu::insert(menu.clone())
-            .exec(&transaction)
-            .await
-            .or_else(|e| {
-                tracing::error!("{:?}", e);
-                Err(AppError::Other("添加菜单失败"))
-            })?;
+        let menu = menu.insert(&transaction).await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("添加菜单失败")
+        })?;
         transaction.commit().await?;
-        Ok(R::ok(menu.try_into_model()?))
+        PermUtils::invalidate(&app_state).await?;
+        Ok(R::ok(menu))
     }
 
-    /// 删除菜单
+    /// 删除菜单。存在子菜单时不允许删除
     pub async fn remove(
         Extension(app_state): Extension<Arc<AppState>>,
-        Extension(_request_state): Extension<Arc<RequestState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(menu): AppJson<MenuBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let menu = Role::find_by_id(menu.uid)
+        let menu = app_state.find_by_id::<Menu>(menu.uid)
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?
             .ok_or_else(|| AppError::Other("未找到菜单信息"))?;
+        let children = app_state.find::<Menu>()
+            .filter(menu::Column::ParentId.eq(menu.uid))
+            .count(&app_state.db.connection)
+            .await?;
+        if children > 0 {
+            return Err(AppError::Other("存在子菜单，无法删除"));
+        }
         let transaction = app_state.begin().await?;
-        menu.clone().delete(&transaction).await.or_else(|e| {
+        app_state.delete_many::<RoleMenu>()
+            .filter(role_menu::Column::MenuId.eq(menu.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空菜单角色关联失败")
+            })?;
+        menu.clone().delete(&transaction).await.map_err(|e| {
             tracing::error!("{:?}", e);
-            Err(AppError::Other("删除菜单失败"))
+            AppError::Other("删除菜单失败")
         })?;
         transaction.commit().await?;
-        Ok(R::ok(menu.try_into_model()?))
+        PermUtils::invalidate(&app_state).await?;
+        Ok(R::ok(menu))
     }
 
     /// 编辑菜单
@@ -66,40 +100,144 @@ impl MenuHandler {
         Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(update_menu): AppJson<MenuBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let mut menu = Role::find_by_id(update_menu.uid)
-            .one(&app_state.db.connection)
+        let menu_type = Self::menu_type(&update_menu.r#type)?;
+        let transaction = app_state.begin().await?;
+        // 父菜单校验与更新在同一事务中，避免并发移动菜单形成环
+        Self::check_parent(&app_state, &transaction, Some(update_menu.uid), update_menu.parent_id).await?;
+        let mut menu = app_state.find_by_id::<Menu>(update_menu.uid)
+            .scoped(&request_state.data_scope)
+            .one(&transaction)
             .await?
             .ok_or_else(|| AppError::Other("未找到菜单信息"))?;
-        let transaction = app_state.begin().await?;
         menu.value = update_menu.value;
         menu.name = update_menu.name;
+        menu.r#type = menu_type.to_string();
+        menu.parent_id = update_menu.parent_id;
+        menu.sort = update_menu.sort;
+        menu.path = update_menu.path;
+        menu.component = update_menu.component;
+        menu.icon = update_menu.icon;
+        menu.visible = update_menu.visible.unwrap_or(menu.visible);
         menu.updater_id = Some(request_state.login_user.uid);
         menu.update_time = Some(Utc::now());
         menu.clone()
             .into_active_model()
+            .reset_all()
             .update(&transaction)
             .await
-            .or_else(|e| {
+            .map_err(|e| {
                 tracing::error!("{:?}", e);
-                Err(AppError::Other("更新菜单失败"))
+                AppError::Other("更新菜单失败")
             })?;
         transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
         Ok(R::ok(menu))
     }
 
+    /// 菜单树，同级按sort升序
+    pub async fn tree(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let menus = app_state.find::<Menu>()
+            .scoped(&request_state.data_scope)
+            .order_by_asc(menu::Column::Sort)
+            .order_by_asc(menu::Column::Uid)
+            .all(&app_state.db.connection)
+            .await?;
+        Ok(R::ok(TreeUtils::build(menus, |menu| menu.uid, |menu| menu.parent_id)))
+    }
+
     /// 查询菜单
     pub async fn info(
         Extension(app_state): Extension<Arc<AppState>>,
-        Extension(_request_state): Extension<Arc<RequestState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppQuery(menu): AppQuery<MenuQuery>,
     ) -> Result<impl IntoResponse, AppError> {
         if menu.uid.is_none() {
             return Err(AppError::Other("uid不能为空"));
         }
-        let menu = Role::find_by_id(menu.uid.unwrap())
+        let menu = app_state.find_by_id::<Menu>(menu.uid.unwrap())
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?
             .ok_or_else(|| AppError::Other("未找到菜单信息"))?;
         Ok(R::ok(menu))
     }
+
+    /// 分页查询菜单
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(menu): AppQuery<MenuQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut select = app_state.find::<Menu>().scoped(&request_state.data_scope);
+        if let Some(uid) = menu.uid {
+            select = select.filter(menu::Column::Uid.eq(uid));
+        }
+        let select = select
+            .filter_text(menu::Column::Name, menu.name, page.fuzzy)
+            .filter_text(menu::Column::Value, menu.value, page.fuzzy)
+            .filter_create_time(menu::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[
+                    ("uid", menu::Column::Uid),
+                    ("name", menu::Column::Name),
+                    ("value", menu::Column::Value),
+                    ("create_time", menu::Column::CreateTime),
+                    ("update_time", menu::Column::UpdateTime),
+                ],
+                menu::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
+    /// 校验菜单类型，未指定时为菜单
+    fn menu_type(menu_type: &str) -> Result<MenuType, AppError> {
+        if menu_type.is_empty() {
+            return Ok(MenuType::Menu);
+        }
+        MenuType::from_str(menu_type).ok_or(AppError::Other("菜单类型只能为menu或button"))
+    }
+
+    /// 校验父菜单存在且不为按钮，编辑时还需校验不会形成环。
+    /// 需在事务中调用，读取时锁定当前租户的菜单，并发的修改在事务提交前等待
+    async fn check_parent<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+        uid: Option<i64>,
+        parent_id: i64,
+    ) -> Result<(), AppError> {
+        if parent_id == 0 {
+            return Ok(());
+        }
+        let menus = app_state.find::<Menu>()
+            .select_only()
+            .column(menu::Column::Uid)
+            .column(menu::Column::ParentId)
+            .column(menu::Column::Type)
+            .lock_exclusive()
+            .into_tuple::<(i64, i64, String)>()
+            .all(connection)
+            .await?;
+        let parent = menus
+            .iter()
+            .find(|(menu_id, _, _)| *menu_id == parent_id)
+            .ok_or(AppError::Other("未找到父菜单信息"))?;
+        if parent.2 == MenuType::Button.to_string() {
+            return Err(AppError::Other("按钮下不能添加子菜单"));
+        }
+        if let Some(uid) = uid {
+            let parents = menus
+                .iter()
+                .map(|(menu_id, parent_id, _)| (*menu_id, *parent_id))
+                .collect::<HashMap<_, _>>();
+            if TreeUtils::has_cycle(&parents, &uid, &parent_id) {
+                return Err(AppError::Other("不能将菜单移动到自身或其子菜单下"));
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,132 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::core::result::{AppJson, R};
+use crate::entity::mfa::{MfaCodeBody, MfaEnrollVo, MfaResetBody};
+use crate::entity::prelude::User;
+use crate::entity::user;
+use crate::state::{AppState, RequestState};
+use crate::utils::mfa::{MfaUtils, ENROLL_EXPIRE};
+use crate::utils::scope::ScopeSelect;
+use crate::utils::session::SessionUtils;
+use axum::response::IntoResponse;
+use axum::Extension;
+use chrono::Utc;
+use rato_core::database::DbPool;
+use rato_core::redis::RedisPool;
+use sea_orm::{ActiveModelTrait, Set};
+use std::sync::Arc;
+
+/// 两步验证handler
+pub struct MfaHandler;
+
+#[allow(unused)]
+impl MfaHandler {
+    /// 生成两步验证密钥，需在有效期内提交动态验证码确认后才生效
+    pub async fn enroll(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user = Self::current_user(&app_state, &request_state).await?;
+        if user.mfa_enabled {
+            return Err(AppError::Other("已启用两步验证，请先重置"));
+        }
+        let secret = MfaUtils::secret();
+        let uri = MfaUtils::uri(&app_state.env.mfa_issuer, &user.account, &secret);
+        let qr_code = MfaUtils::qr_code(&uri)?;
+        app_state
+            .set_ex(RedisKey::mfa_enroll(user.uid), &secret, ENROLL_EXPIRE)
+            .await?;
+        Ok(R::ok(MfaEnrollVo { secret, uri, qr_code }))
+    }
+
+    /// 提交首个动态验证码确认绑定，返回恢复码。恢复码仅返回这一次
+    pub async fn confirm(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<MfaCodeBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user = Self::current_user(&app_state, &request_state).await?;
+        if user.mfa_enabled {
+            return Err(AppError::Other("已启用两步验证，请先重置"));
+        }
+        // 取出即删除，并发的确认请求只有一个能取得密钥，验证码错误时需重新绑定
+        let secret = app_state
+            .get_del::<_, String>(RedisKey::mfa_enroll(user.uid))
+            .await?
+            .ok_or(AppError::Other("两步验证密钥已过期，请重新绑定"))?;
+        let Some(step) = MfaUtils::verify_totp(&secret, body.code.trim(), Utc::now().timestamp()) else {
+            return Err(AppError::Other("动态验证码错误，请重新绑定"));
+        };
+        if !MfaUtils::consume_step(&app_state, user.uid, step).await? {
+            return Err(AppError::Other("动态验证码已使用，请重新绑定"));
+        }
+        let recovery_codes = MfaUtils::recovery_codes();
+        let transaction = app_state.begin().await?;
+        // 只更新两步验证相关的字段，不覆盖并发修改的其他字段
+        user::ActiveModel {
+            uid: Set(user.uid),
+            mfa_enabled: Set(true),
+            mfa_secret: Set(Some(secret)),
+            updater_id: Set(Some(request_state.login_user.uid)),
+            update_time: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .update(&transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("启用两步验证失败")
+        })?;
+        MfaUtils::save_recovery_codes(&app_state, &transaction, user.uid, &recovery_codes).await?;
+        transaction.commit().await?;
+        tracing::info!(target: "audit", "用户{}启用两步验证", user.uid);
+        Ok(R::ok(recovery_codes))
+    }
+
+    /// 管理员重置用户的两步验证，用户需重新绑定
+    pub async fn reset(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<MfaResetBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let user = app_state
+            .find_by_id::<User>(body.user_uid)
+            .scoped(&request_state.data_scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))?;
+        let transaction = app_state.begin().await?;
+        user::ActiveModel {
+            uid: Set(user.uid),
+            mfa_enabled: Set(false),
+            mfa_secret: Set(None),
+            updater_id: Set(Some(request_state.login_user.uid)),
+            update_time: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .update(&transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("重置两步验证失败")
+        })?;
+        MfaUtils::remove_recovery_codes(&app_state, &transaction, user.uid).await?;
+        transaction.commit().await?;
+        let _ = app_state.del(RedisKey::mfa_enroll(user.uid)).await;
+        // 重置前签发的会话经过两步验证，需重新登录
+        let count = SessionUtils::remove_all(&app_state, user.uid).await?;
+        tracing::warn!(target: "audit", "重置用户{}的两步验证，撤销{}个会话，操作人：{}", user.uid, count, request_state.login_user.uid);
+        Ok(R::ok(true))
+    }
+
+    async fn current_user(
+        app_state: &AppState,
+        request_state: &RequestState,
+    ) -> Result<user::Model, AppError> {
+        app_state
+            .find_by_id::<User>(request_state.login_user.uid)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))
+    }
+}
@@ -1,4 +1,12 @@
+pub mod captcha_handler;
+pub mod dept_handler;
+pub mod file_handler;
+pub mod login_handler;
 pub mod menu_handler;
+pub mod mfa_handler;
+pub mod oper_log_handler;
 pub mod role_handler;
+pub mod session_handler;
+pub mod tenant_handler;
 pub mod token_handler;
 pub mod user_handler;
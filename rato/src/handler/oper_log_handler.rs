@@ -0,0 +1,88 @@
+use crate::core::error::AppError;
+use crate::core::page::{PageQuery, PageSelect};
+use crate::core::result::{AppQuery, R};
+use crate::entity::oper_log;
+use crate::entity::oper_log::OperLogQuery;
+use crate::entity::prelude::OperLog;
+use crate::state::{AppState, RequestState};
+use crate::utils::oper_log::OperLogUtils;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::IntoResponse;
+use axum::Extension;
+use sea_orm::{ColumnTrait, QueryFilter, QuerySelect, Select};
+use std::sync::Arc;
+
+/// 单次导出的最大条数
+const EXPORT_MAX: u64 = 10_000;
+
+/// 操作日志handler
+pub struct OperLogHandler;
+
+#[allow(unused)]
+impl OperLogHandler {
+    /// 分页查询操作日志
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(_request_state): Extension<Arc<RequestState>>,
+        AppQuery(log): AppQuery<OperLogQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let select = Self::select(&app_state, log, &page)?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
+    /// 按查询条件导出操作日志为CSV，最多导出10000条
+    pub async fn export(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(log): AppQuery<OperLogQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let logs = Self::select(&app_state, log, &page)?
+            .limit(EXPORT_MAX)
+            .all(&app_state.db.connection)
+            .await?;
+        tracing::info!(target: "audit", "用户{}导出操作日志{}条", request_state.login_user.uid, logs.len());
+        Ok((
+            [
+                (CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (CONTENT_DISPOSITION, "attachment; filename=\"oper_log.csv\""),
+            ],
+            OperLogUtils::csv(&logs),
+        ))
+    }
+
+    fn select(
+        app_state: &AppState,
+        log: OperLogQuery,
+        page: &PageQuery,
+    ) -> Result<Select<OperLog>, AppError> {
+        let mut select = app_state.find::<OperLog>();
+        if let Some(user_id) = log.user_id {
+            select = select.filter(oper_log::Column::UserId.eq(user_id));
+        }
+        if let Some(code) = log.code {
+            select = select.filter(oper_log::Column::Code.eq(code));
+        }
+        if let Some(ip) = log.ip {
+            select = select.filter(oper_log::Column::Ip.eq(ip));
+        }
+        if let Some(bypass) = log.bypass {
+            select = select.filter(oper_log::Column::Bypass.eq(bypass));
+        }
+        select
+            .filter_text(oper_log::Column::Account, log.account, page.fuzzy)
+            .filter_text(oper_log::Column::Path, log.path, page.fuzzy)
+            .filter_text(oper_log::Column::Perm, log.perm, page.fuzzy)
+            .filter_create_time(oper_log::Column::CreateTime, page)
+            .sort_by(
+                page,
+                &[
+                    ("uid", oper_log::Column::Uid),
+                    ("latency", oper_log::Column::Latency),
+                    ("create_time", oper_log::Column::CreateTime),
+                ],
+                oper_log::Column::CreateTime,
+            )
+    }
+}
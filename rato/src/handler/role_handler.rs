@@ -1,15 +1,20 @@
-use crate::entity::prelude::{Role, RoleMenu};
-use crate::entity::role::{AuthPermBody, RoleBody, RoleQuery};
-use crate::entity::{role, role_menu};
+use crate::entity::prelude::{Dept, Menu, Role, RoleDept, RoleMenu, RoleParent, UserRole};
+use crate::entity::role::{AuthPermBody, DataScopeBody, EffectivePermVo, RoleBody, RoleEffectiveVo, RoleParentBody, RoleQuery};
+use crate::entity::{dept, menu, role, role_dept, role_menu, role_parent, user_role};
 use crate::core::error::AppError;
+use crate::core::page::{PageQuery, PageSelect};
 use crate::core::result::{AppJson, AppQuery, R};
 use axum::response::IntoResponse;
 use axum::Extension;
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, QueryFilter, Set, ColumnTrait, QuerySelect};
+use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, Set, ColumnTrait, QuerySelect};
+use std::collections::HashSet;
 use std::sync::Arc;
 use rato_core::database::DbPool;
 use crate::state::{AppState, RequestState};
+use crate::utils::tenant::TenantUtils;
+use crate::utils::perm::PermUtils;
+use crate::utils::scope::{DataScopeType, ScopeSelect};
 
 /// 角色handler
 pub struct RoleHandler;
@@ -21,37 +26,85 @@ impl RoleHandler {
         Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(role): AppJson<RoleBody>,
     ) -> Result<impl IntoResponse, AppError> {
+        Self::check_super_admin(&app_state, &request_state, &role.value)?;
         let transaction = app_state.begin().await?;
         let role = role::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
             name: Set(role.name),
             value: Set(role.value),
             creator_id: Set(request_state.login_user.uid),
             create_time: Set(Utc::now()),
             ..Default::default()
         };
-        let role = role.insert(&transaction).await.or_else(|e|{
+        let role = role.insert(&transaction).await.map_err(|e|{
             tracing::error!("{:?}", e);
-            Err(AppError::Other("添加角色失败"))
+            AppError::Other("添加角色失败")
         })?;
         transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
         Ok(R::ok(role))
     }
 
     pub async fn remove(
         Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(role): AppJson<RoleBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let role = Role::find_by_id(role.uid)
+        let role = app_state.find_by_id::<Role>(role.uid)
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?.ok_or_else(|| {
             AppError::Other("未找到角色信息")
         })?;
         let transaction = app_state.begin().await?;
-        Role::delete_by_id(role.uid).exec(&transaction).await.or_else(|e|{
-            tracing::error!("{:?}", e);
-            Err(AppError::Other("删除角色失败"))
-        })?;
+        // 清空以该角色为子角色或父角色的继承关系
+        app_state.delete_many::<RoleParent>()
+            .filter(
+                role_parent::Column::RoleId
+                    .eq(role.uid)
+                    .or(role_parent::Column::ParentId.eq(role.uid)),
+            )
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空角色继承关系失败")
+            })?;
+        // 清空角色的菜单权限、用户及数据范围部门关联
+        app_state.delete_many::<RoleMenu>()
+            .filter(role_menu::Column::RoleId.eq(role.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空角色菜单权限失败")
+            })?;
+        app_state.delete_many::<UserRole>()
+            .filter(user_role::Column::RoleId.eq(role.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空角色用户失败")
+            })?;
+        app_state.delete_many::<RoleDept>()
+            .filter(role_dept::Column::RoleId.eq(role.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空角色数据范围部门失败")
+            })?;
+        app_state.delete_many::<Role>()
+            .filter(role::Column::Uid.eq(role.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("删除角色失败")
+            })?;
         transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
         Ok(R::ok(role))
     }
 
@@ -60,11 +113,15 @@ impl RoleHandler {
         Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(update_role): AppJson<RoleBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let mut role = Role::find_by_id(update_role.uid)
+        let mut role = app_state.find_by_id::<Role>(update_role.uid)
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?.ok_or_else(||{
             AppError::Other("未找到角色信息")
         })?;
+        // 不能将角色改为超级管理员角色，也不能修改超级管理员角色本身
+        Self::check_super_admin(&app_state, &request_state, &update_role.value)?;
+        Self::check_super_admin(&app_state, &request_state, &role.value)?;
         let transaction = app_state.begin().await?;
         role.value = update_role.value;
         role.name = update_role.name;
@@ -74,23 +131,25 @@ impl RoleHandler {
             .into_active_model()
             .update(&transaction)
             .await
-            .or_else(|e| {
+            .map_err(|e| {
                 tracing::error!("{:?}", e);
-                Err(AppError::Other("更新角色失败"))
+                AppError::Other("更新角色失败")
             })?;
         transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
         Ok(R::ok(role))
     }
 
     pub async fn info(
         Extension(app_state): Extension<Arc<AppState>>,
-        Extension(_request_state): Extension<Arc<RequestState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppQuery(role): AppQuery<RoleQuery>,
     ) -> Result<impl IntoResponse, AppError> {
         if role.uid.is_none() {
             return Err(AppError::Other("uid不能为空"));
         }
-        let role = Role::find_by_id(role.uid.unwrap())
+        let role = app_state.find_by_id::<Role>(role.uid.unwrap())
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?.ok_or_else(||{
             AppError::Other("未找到角色信息")
@@ -98,31 +157,71 @@ impl RoleHandler {
         Ok(R::ok(role))
     }
 
+    /// 分页查询角色
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(role): AppQuery<RoleQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut select = app_state.find::<Role>().scoped(&request_state.data_scope);
+        if let Some(uid) = role.uid {
+            select = select.filter(role::Column::Uid.eq(uid));
+        }
+        let select = select
+            .filter_text(role::Column::Name, role.name, page.fuzzy)
+            .filter_text(role::Column::Value, role.value, page.fuzzy)
+            .filter_create_time(role::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[
+                    ("uid", role::Column::Uid),
+                    ("name", role::Column::Name),
+                    ("value", role::Column::Value),
+                    ("create_time", role::Column::CreateTime),
+                    ("update_time", role::Column::UpdateTime),
+                ],
+                role::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
     pub async fn auth_perm(
         Extension(app_state): Extension<Arc<AppState>>,
-        Extension(_request_state): Extension<Arc<RequestState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(auth_perm): AppJson<AuthPermBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let role = Role::find_by_id(auth_perm.role_id)
+        let role = app_state.find_by_id::<Role>(auth_perm.role_id)
+            .scoped(&request_state.data_scope)
             .select_only()
             .column(role::Column::Uid)
             .one(&app_state.db.connection)
             .await?.ok_or_else(||{
             AppError::Other("未找到角色信息")
         })?;
+        // 只能分配当前租户的菜单
+        let menu_uids = auth_perm.perm_uids.iter().collect::<HashSet<_>>();
+        let count = app_state.find::<Menu>()
+            .filter(menu::Column::Uid.is_in(menu_uids.iter().copied().copied()))
+            .count(&app_state.db.connection)
+            .await?;
+        if count as usize != menu_uids.len() {
+            return Err(AppError::Other("未找到菜单信息"));
+        }
         let transaction = app_state.begin().await?;
-        RoleMenu::delete_many()
+        app_state.delete_many::<RoleMenu>()
             .filter(role_menu::Column::RoleId.eq(role.uid))
             .exec(&transaction)
             .await
-            .or_else(|e| {
+            .map_err(|e| {
                 tracing::error!("{:?}", e);
-                Err(AppError::Other("清空角色菜单权限失败"))
+                AppError::Other("清空角色菜单权限失败")
             })?;
         let models = auth_perm
             .perm_uids
             .iter()
             .map(|menu_id| role_menu::ActiveModel {
+                tenant_id: Set(TenantUtils::current()),
                 menu_id: Set(*menu_id),
                 role_id: Set(role.uid),
             })
@@ -130,11 +229,204 @@ impl RoleHandler {
         RoleMenu::insert_many(models)
             .exec(&transaction)
             .await
-            .or_else(|e| {
+            .map_err(|e| {
                 tracing::error!("{:?}", e);
-                Err(AppError::Other("分配角色菜单权限失败"))
+                AppError::Other("分配角色菜单权限失败")
             })?;
         transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
         Ok(R::ok(auth_perm.perm_uids.len()))
     }
+
+    /// 设置角色的父角色，角色继承父角色的全部权限。继承关系成环时拒绝
+    pub async fn parent(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<RoleParentBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut parent_uids = body.parent_uids.clone();
+        parent_uids.sort_unstable();
+        parent_uids.dedup();
+        let mut role_uids = parent_uids.clone();
+        role_uids.push(body.role_id);
+        let transaction = app_state.begin().await?;
+        // 锁定租户的全部角色，串行化继承关系变更，避免并发设置时绕过成环检查
+        app_state.find::<Role>()
+            .select_only()
+            .column(role::Column::Uid)
+            .lock_exclusive()
+            .into_tuple::<i64>()
+            .all(&transaction)
+            .await?;
+        // 角色及父角色均需在当前用户的数据范围内
+        let count = app_state.find::<Role>()
+            .scoped(&request_state.data_scope)
+            .filter(role::Column::Uid.is_in(role_uids.clone()))
+            .count(&transaction)
+            .await?;
+        if count as usize != role_uids.iter().collect::<HashSet<_>>().len() {
+            return Err(AppError::Other("未找到角色信息"));
+        }
+        let parents = PermUtils::role_parents(&app_state, &transaction).await?;
+        if PermUtils::has_cycle(&parents, body.role_id, &parent_uids) {
+            return Err(AppError::Other("角色继承关系不能成环"));
+        }
+        app_state.delete_many::<RoleParent>()
+            .filter(role_parent::Column::RoleId.eq(body.role_id))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空父角色失败")
+            })?;
+        if !parent_uids.is_empty() {
+            let models = parent_uids
+                .iter()
+                .map(|parent_id| role_parent::ActiveModel {
+                    tenant_id: Set(TenantUtils::current()),
+                    role_id: Set(body.role_id),
+                    parent_id: Set(*parent_id),
+                })
+                .collect::<Vec<_>>();
+            RoleParent::insert_many(models)
+                .exec(&transaction)
+                .await
+                .map_err(|e| {
+                    tracing::error!("{:?}", e);
+                    AppError::Other("设置父角色失败")
+                })?;
+        }
+        transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
+        Ok(R::ok(parent_uids.len()))
+    }
+
+    /// 角色的有效权限，包含继承的权限及其来源角色
+    pub async fn effective(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(role): AppQuery<RoleQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let uid = role.uid.ok_or(AppError::Other("uid不能为空"))?;
+        let connection = &app_state.db.connection;
+        let role = app_state.find_by_id::<Role>(uid)
+            .scoped(&request_state.data_scope)
+            .one(connection)
+            .await?
+            .ok_or(AppError::Other("未找到角色信息"))?;
+        let closure = PermUtils::ancestors(&PermUtils::role_parents(&app_state, connection).await?, [role.uid]);
+        let mut roles = app_state.find::<Role>()
+            .filter(role::Column::Uid.is_in(closure.clone()))
+            .all(connection)
+            .await?;
+        // 按继承层级排序，角色自身在前
+        roles.sort_by_key(|item| closure.iter().position(|uid| *uid == item.uid));
+        let grants = app_state.find::<RoleMenu>()
+            .filter(role_menu::Column::RoleId.is_in(closure))
+            .all(connection)
+            .await?;
+        let menus = app_state.find::<Menu>()
+            .filter(menu::Column::Uid.is_in(grants.iter().map(|grant| grant.menu_id).collect::<HashSet<_>>()))
+            .order_by_asc(menu::Column::Sort)
+            .order_by_asc(menu::Column::Uid)
+            .all(connection)
+            .await?;
+        let perms = menus
+            .into_iter()
+            .map(|menu| {
+                let sources = roles
+                    .iter()
+                    .filter(|item| {
+                        grants
+                            .iter()
+                            .any(|grant| grant.role_id == item.uid && grant.menu_id == menu.uid)
+                    })
+                    .map(|item| item.value.clone())
+                    .collect::<Vec<_>>();
+                EffectivePermVo {
+                    menu_id: menu.uid,
+                    inherited: !sources.contains(&role.value),
+                    value: menu.value,
+                    name: menu.name,
+                    sources,
+                }
+            })
+            .collect();
+        Ok(R::ok(RoleEffectiveVo { roles, perms }))
+    }
+
+    /// 设置角色的数据范围
+    pub async fn data_scope(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<DataScopeBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let data_scope = DataScopeType::from_str(&body.data_scope)
+            .ok_or(AppError::Other("数据范围只能为all、self、dept或custom"))?;
+        let mut role = app_state.find_by_id::<Role>(body.role_id)
+            .scoped(&request_state.data_scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到角色信息"))?;
+        let dept_uids = body.dept_uids.iter().collect::<HashSet<_>>();
+        if data_scope == DataScopeType::Custom {
+            let count = app_state.find::<Dept>()
+                .filter(dept::Column::Uid.is_in(dept_uids.iter().copied().copied()))
+                .count(&app_state.db.connection)
+                .await?;
+            if count as usize != dept_uids.len() {
+                return Err(AppError::Other("未找到部门信息"));
+            }
+        }
+        let transaction = app_state.begin().await?;
+        role.data_scope = data_scope.to_string();
+        role.updater_id = Some(request_state.login_user.uid);
+        role.update_time = Some(Utc::now());
+        role.clone()
+            .into_active_model()
+            .reset_all()
+            .update(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("更新角色数据范围失败")
+            })?;
+        app_state.delete_many::<RoleDept>()
+            .filter(role_dept::Column::RoleId.eq(role.uid))
+            .exec(&transaction)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("清空角色数据范围部门失败")
+            })?;
+        if data_scope == DataScopeType::Custom && !dept_uids.is_empty() {
+            let models = dept_uids
+                .into_iter()
+                .map(|dept_id| role_dept::ActiveModel {
+                    tenant_id: Set(TenantUtils::current()),
+                    role_id: Set(role.uid),
+                    dept_id: Set(*dept_id),
+                })
+                .collect::<Vec<_>>();
+            RoleDept::insert_many(models)
+                .exec(&transaction)
+                .await
+                .map_err(|e| {
+                    tracing::error!("{:?}", e);
+                    AppError::Other("设置角色数据范围部门失败")
+                })?;
+        }
+        transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
+        Ok(R::ok(role))
+    }
+
+    /// 角色值为超级管理员角色时，仅超级管理员可以设置
+    fn check_super_admin(app_state: &AppState, request_state: &RequestState, value: &str) -> Result<(), AppError> {
+        if value == app_state.env.super_admin_role && !PermUtils::is_super_admin(app_state, &request_state.login_user) {
+            tracing::warn!(target: "audit", "非超级管理员尝试设置超级管理员角色，用户：{}", request_state.login_user.uid);
+            return Err(AppError::Other("无权设置超级管理员角色"));
+        }
+        Ok(())
+    }
 }
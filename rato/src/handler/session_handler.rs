@@ -0,0 +1,52 @@
+use crate::core::error::AppError;
+use crate::core::result::{AppJson, R};
+use crate::entity::session::{SessionBody, SessionVo};
+use crate::state::{AppState, RequestState};
+use crate::utils::session::SessionUtils;
+use axum::response::IntoResponse;
+use axum::Extension;
+use std::sync::Arc;
+
+/// 登录会话 handler
+pub struct SessionHandler;
+
+#[allow(unused)]
+impl SessionHandler {
+    /// 当前用户的全部登录会话
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let sessions = SessionUtils::list(&app_state, request_state.login_user.uid)
+            .await?
+            .into_iter()
+            .map(|session| SessionVo::new(session, &request_state.jti))
+            .collect::<Vec<_>>();
+        Ok(R::ok(sessions))
+    }
+
+    /// 撤销当前用户的指定会话
+    pub async fn revoke(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<SessionBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let uid = request_state.login_user.uid;
+        // 只能撤销自己的会话
+        SessionUtils::get(&app_state, &body.sid)
+            .await?
+            .filter(|session| session.uid == uid)
+            .ok_or(AppError::Other("会话不存在或已失效"))?;
+        SessionUtils::remove(&app_state, uid, &body.sid).await?;
+        Ok(R::ok(true))
+    }
+
+    /// 撤销当前用户除当前会话外的全部会话
+    pub async fn revoke_others(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let count = SessionUtils::remove_others(&app_state, request_state.login_user.uid, &request_state.jti).await?;
+        Ok(R::ok(count))
+    }
+}
@@ -0,0 +1,261 @@
+use crate::core::error::AppError;
+use crate::core::page::{PageQuery, PageSelect};
+use crate::core::result::{AppJson, AppQuery, R};
+use crate::entity::prelude::{Menu, RoleMenu, Tenant, UserRole};
+use crate::entity::tenant::{TenantBody, TenantQuery};
+use crate::entity::{menu, role, role_menu, tenant, user, user_role};
+use crate::state::{AppState, RequestState};
+use crate::utils::password::PasswordUtils;
+use crate::utils::tenant::TenantUtils;
+use crate::utils::tree::TreeUtils;
+use axum::response::IntoResponse;
+use axum::Extension;
+use chrono::Utc;
+use rato_core::database::DbPool;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, Set,
+};
+use std::sync::Arc;
+
+/// 租户handler。仅平台租户可以访问
+pub struct TenantHandler;
+
+#[allow(unused)]
+impl TenantHandler {
+    /// 创建租户，并初始化租户的菜单、角色及管理员账号
+    pub async fn add(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<TenantBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Self::check_platform()?;
+        if body.code.is_empty() || body.name.is_empty() {
+            return Err(AppError::Other("租户编码及名称不能为空"));
+        }
+        if body.admin_account.is_empty() || body.admin_password.is_empty() {
+            return Err(AppError::Other("租户管理员账号及密码不能为空"));
+        }
+        Self::check_unique(&app_state, 0, &body.code, body.domain.as_deref()).await?;
+        // 租户的初始菜单复制自平台租户
+        let menus = app_state
+            .find::<Menu>()
+            .order_by_asc(menu::Column::Sort)
+            .order_by_asc(menu::Column::Uid)
+            .all(&app_state.db.connection)
+            .await?;
+        let uid = request_state.login_user.uid;
+        let transaction = app_state.begin().await?;
+        let tenant = tenant::ActiveModel {
+            code: Set(body.code.clone()),
+            name: Set(body.name.clone()),
+            domain: Set(body.domain.clone()),
+            enabled: Set(body.enabled.unwrap_or(true)),
+            creator_id: Set(uid),
+            create_time: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("添加租户失败")
+        })?;
+        TenantUtils::scope(tenant.uid, Self::seed(&app_state, &transaction, uid, menus, &body)).await?;
+        transaction.commit().await?;
+        tracing::info!(target: "audit", "创建租户：{}（{}），操作人：{}", tenant.code, tenant.uid, uid);
+        Ok(R::ok(tenant))
+    }
+
+    /// 编辑租户。停用后该租户的请求均被拒绝
+    pub async fn edit(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<TenantBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Self::check_platform()?;
+        let mut tenant = Tenant::find_by_id(body.uid)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到租户信息"))?;
+        Self::check_unique(&app_state, tenant.uid, &body.code, body.domain.as_deref()).await?;
+        tenant.code = body.code;
+        tenant.name = body.name;
+        tenant.domain = body.domain;
+        tenant.enabled = body.enabled.unwrap_or(tenant.enabled);
+        tenant.updater_id = Some(request_state.login_user.uid);
+        tenant.update_time = Some(Utc::now());
+        tenant
+            .clone()
+            .into_active_model()
+            .reset_all()
+            .update(&app_state.db.connection)
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("更新租户失败")
+            })?;
+        TenantUtils::evict(&app_state, tenant.uid).await;
+        Ok(R::ok(tenant))
+    }
+
+    /// 查询租户
+    pub async fn info(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(_request_state): Extension<Arc<RequestState>>,
+        AppQuery(tenant): AppQuery<TenantQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Self::check_platform()?;
+        let uid = tenant.uid.ok_or(AppError::Other("uid不能为空"))?;
+        let tenant = Tenant::find_by_id(uid)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到租户信息"))?;
+        Ok(R::ok(tenant))
+    }
+
+    /// 分页查询租户
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(_request_state): Extension<Arc<RequestState>>,
+        AppQuery(tenant): AppQuery<TenantQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        Self::check_platform()?;
+        let mut select = Tenant::find();
+        if let Some(uid) = tenant.uid {
+            select = select.filter(tenant::Column::Uid.eq(uid));
+        }
+        let select = select
+            .filter_text(tenant::Column::Code, tenant.code, page.fuzzy)
+            .filter_text(tenant::Column::Name, tenant.name, page.fuzzy)
+            .filter_create_time(tenant::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[
+                    ("uid", tenant::Column::Uid),
+                    ("code", tenant::Column::Code),
+                    ("name", tenant::Column::Name),
+                    ("create_time", tenant::Column::CreateTime),
+                    ("update_time", tenant::Column::UpdateTime),
+                ],
+                tenant::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
+    /// 在新租户下初始化菜单、默认角色及管理员账号。超级管理员角色拥有全部初始菜单权限
+    async fn seed<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+        creator_id: i64,
+        menus: Vec<menu::Model>,
+        body: &TenantBody,
+    ) -> Result<(), AppError> {
+        let tenant_id = TenantUtils::current();
+        let now = Utc::now();
+        // 按树的层级插入，父菜单先于子菜单生成新id
+        let mut menu_uids = Vec::with_capacity(menus.len());
+        let mut stack = TreeUtils::build(menus, |menu| menu.uid, |menu| menu.parent_id)
+            .into_iter()
+            .map(|node| (node, 0))
+            .collect::<Vec<_>>();
+        while let Some((node, parent_id)) = stack.pop() {
+            let menu = node.node;
+            let inserted = menu::ActiveModel {
+                tenant_id: Set(tenant_id),
+                value: Set(menu.value),
+                name: Set(menu.name),
+                r#type: Set(menu.r#type),
+                parent_id: Set(parent_id),
+                sort: Set(menu.sort),
+                path: Set(menu.path),
+                component: Set(menu.component),
+                icon: Set(menu.icon),
+                visible: Set(menu.visible),
+                creator_id: Set(creator_id),
+                create_time: Set(now),
+                ..Default::default()
+            }
+            .insert(connection)
+            .await?;
+            menu_uids.push(inserted.uid);
+            stack.extend(node.children.into_iter().map(|child| (child, inserted.uid)));
+        }
+        let mut admin_role = None;
+        for (value, name) in app_state.env.seed_roles() {
+            let role = role::ActiveModel {
+                tenant_id: Set(tenant_id),
+                value: Set(value),
+                name: Set(name),
+                creator_id: Set(creator_id),
+                create_time: Set(now),
+                ..Default::default()
+            }
+            .insert(connection)
+            .await?;
+            if role.value == app_state.env.super_admin_role {
+                admin_role = Some(role.uid);
+            }
+        }
+        let admin_role = admin_role.ok_or(AppError::Other("初始化租户管理员角色失败"))?;
+        if !menu_uids.is_empty() {
+            RoleMenu::insert_many(menu_uids.into_iter().map(|menu_id| role_menu::ActiveModel {
+                tenant_id: Set(tenant_id),
+                role_id: Set(admin_role),
+                menu_id: Set(menu_id),
+            }))
+            .exec(connection)
+            .await?;
+        }
+        let admin = user::ActiveModel {
+            tenant_id: Set(tenant_id),
+            account: Set(body.admin_account.clone()),
+            name: Set(body.admin_account.clone()),
+            password: Set(PasswordUtils::hash_async(&body.admin_password, &app_state.env.password_config()).await?),
+            creator_id: Set(creator_id),
+            create_time: Set(now),
+            ..Default::default()
+        }
+        .insert(connection)
+        .await?;
+        UserRole::insert(user_role::ActiveModel {
+            tenant_id: Set(tenant_id),
+            user_id: Set(admin.uid),
+            role_id: Set(admin_role),
+        })
+        .exec(connection)
+        .await?;
+        Ok(())
+    }
+
+    /// 租户只能由平台租户管理
+    fn check_platform() -> Result<(), AppError> {
+        if !TenantUtils::is_platform() {
+            return Err(AppError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// 校验租户编码及域名不与其他租户重复
+    async fn check_unique(
+        app_state: &AppState,
+        uid: i64,
+        code: &str,
+        domain: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut condition = tenant::Column::Code.eq(code);
+        if let Some(domain) = domain {
+            condition = condition.or(tenant::Column::Domain.eq(domain));
+        }
+        let exists = Tenant::find()
+            .filter(condition)
+            .filter(tenant::Column::Uid.ne(uid))
+            .one(&app_state.db.connection)
+            .await?;
+        if exists.is_some() {
+            return Err(AppError::Other("租户编码或域名已存在"));
+        }
+        Ok(())
+    }
+}
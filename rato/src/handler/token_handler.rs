@@ -1,19 +1,27 @@
 use crate::core::error::AppError;
-use crate::core::result::{AppJson, R};
+use crate::core::result::{AppJson, ClientInfo, R};
 use crate::utils::jwt::JwtUtils;
 use axum::response::IntoResponse;
-use axum::{Extension};
+use axum::{Extension, Json};
 use std::sync::Arc;
 use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, LoaderTrait, ModelTrait, QueryFilter, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, QueryFilter, Set, SqlErr};
 use rato_core::database::DbPool;
 use rato_core::redis::RedisPool;
-use crate::entity::prelude::{Menu, Role, RoleMenu, User};
+use crate::entity::prelude::User;
 use crate::entity::{user};
+use crate::entity::session::SessionInfo;
+use crate::entity::mfa::{MfaLoginBody, MfaPending};
+use crate::entity::token::{RefreshBody, RefreshRecord, TokenPair};
 use crate::entity::user::{LoginUserBuilder, UserBody};
-use crate::core::constant::{APP_NAME, LOGIN_UID};
+use crate::core::constant::RedisKey;
 use crate::state::{AppState, RequestState};
-use crate::utils::Utils;
+use crate::utils::tenant::TenantUtils;
+use crate::utils::captcha::CaptchaUtils;
+use crate::utils::login::LoginGuard;
+use crate::utils::mfa::MfaUtils;
+use crate::utils::password::{PasswordUtils, PasswordVerify};
+use crate::utils::session::SessionUtils;
 
 /// token handler
 pub struct TokenHandler;
@@ -25,19 +33,29 @@ impl TokenHandler {
         Extension(app_state): Extension<Arc<AppState>>,
         AppJson(register): AppJson<UserBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let transaction = app_state.begin().await?;
-        let filter_users = User::find().filter(user::Column::Account.eq(&register.account)).all(&app_state.db.connection).await?;
+        if app_state.env.captcha_enabled {
+            CaptchaUtils::verify(&app_state, register.captcha_id.as_deref(), register.captcha.as_deref()).await?;
+        }
+        let filter_users = app_state.find::<User>().filter(user::Column::Account.eq(&register.account)).all(&app_state.db.connection).await?;
         if !filter_users.is_empty() {
             return Err(AppError::Other("账号已存在，注册失败"));
         }
+        // 哈希耗时较长，计算完成后再开启事务
+        let password = PasswordUtils::hash_async(&register.password, &app_state.env.password_config()).await?;
+        let transaction = app_state.begin().await?;
+        // 并发注册同一账号时由唯一索引拦截，上面的查询只用于提前返回
         let user = user::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
             account: Set(register.account),
             name: Set(register.name),
-            password: Set(register.password),
+            password: Set(password),
             creator_id: Set(-1),
             create_time: Set(Utc::now()),
             ..Default::default()
-        }.insert(&transaction).await?;
+        }.insert(&transaction).await.map_err(|e| match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => AppError::Other("账号已存在，注册失败"),
+            _ => AppError::DbError(e),
+        })?;
         let update_user = user::ActiveModel {
             uid: Set(user.uid),
             creator_id: Set(user.uid),
@@ -52,27 +70,111 @@ impl TokenHandler {
 
     pub async fn login(
         Extension(app_state): Extension<Arc<AppState>>,
+        client: ClientInfo,
         AppJson(login): AppJson<UserBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let transaction = app_state.begin().await?;
-        let filter_users = User::find().filter(user::Column::Account.eq(login.account)).all(&app_state.db.connection).await?;
-        if filter_users.is_empty() {
-            return Err(AppError::Other("账号或密码错误"))
+        // 账号或ip锁定期间直接拒绝，不再校验密码
+        let guard = LoginGuard::new(&app_state, &login.account, &client);
+        guard.check().await?;
+        if guard.captcha_required().await? {
+            CaptchaUtils::verify(&app_state, login.captcha_id.as_deref(), login.captcha.as_deref()).await?;
+        }
+        let user = app_state.find::<User>().filter(user::Column::Account.eq(&login.account)).one(&app_state.db.connection).await?;
+        let password_config = app_state.env.password_config();
+        // 账号不存在时同样执行一次校验，避免通过耗时判断账号是否存在
+        let hashed = user.as_ref().map(|user| user.password.as_str());
+        let verify = PasswordUtils::verify_async(&login.password, hashed, &password_config).await;
+        let user = match user {
+            Some(user) if !matches!(verify, PasswordVerify::Invalid) => user,
+            user => {
+                guard.record(user.map(|user| user.uid), false, "账号或密码错误").await;
+                guard.fail().await?;
+                return Err(AppError::Other("账号或密码错误"));
+            }
+        };
+        if let PasswordVerify::NeedsRehash = verify {
+            // 明文或参数过时的密码，登录成功后原地升级。哈希耗时较长，计算完成后再开启事务
+            let password = PasswordUtils::hash_async(&login.password, &password_config).await?;
+            let transaction = app_state.begin().await?;
+            user::ActiveModel {
+                uid: Set(user.uid),
+                password: Set(password),
+                ..Default::default()
+            }
+            .update(&transaction)
+            .await?;
+            transaction.commit().await?;
+        }
+        // 密码校验通过后再检查账号状态，避免泄露账号是否存在
+        if let Some(reason) = user.inactive_reason(Utc::now()) {
+            guard.record(Some(user.uid), false, reason).await;
+            return Err(AppError::Other(reason));
+        }
+        if user.mfa_enabled {
+            // 两步验证通过前不清除失败记录，动态验证码错误同样计入失败次数
+            return Ok(R::ok(MfaUtils::challenge(&app_state, &user).await?).into_response());
+        }
+        guard.succeed().await;
+        let token_pair = Self::sign_in(&app_state, &user, &client).await?;
+        guard.record(Some(user.uid), true, "登录成功").await;
+        Ok(R::ok(token_pair).into_response())
+    }
+
+    /// 两步验证登录，使用账号密码校验后返回的临时令牌及动态验证码或恢复码换取令牌
+    pub async fn mfa(
+        Extension(app_state): Extension<Arc<AppState>>,
+        client: ClientInfo,
+        AppJson(body): AppJson<MfaLoginBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        // 在临时令牌所属的租户下校验，不依赖请求头中的租户
+        let tenant_id = JwtUtils::token_tenant(&body.mfa_token)
+            .ok_or(AppError::Relogin("两步验证已过期，请重新登录"))?;
+        let tenant_id = TenantUtils::check(&app_state, tenant_id).await?;
+        let token_pair = TenantUtils::scope(tenant_id, Self::verify_mfa(&app_state, &client, body)).await?;
+        Ok(R::ok(token_pair))
+    }
+
+    async fn verify_mfa(app_state: &AppState, client: &ClientInfo, body: MfaLoginBody) -> Result<TokenPair, AppError> {
+        let key = RedisKey::mfa_pending(&JwtUtils::hash_refresh_token(&body.mfa_token));
+        app_state
+            .exists(&key)
+            .await
+            .map_err(|e| AppError::Relogin("两步验证已过期，请重新登录"))?;
+        let pending = app_state.cached::<MfaPending>(&key).await?;
+        let guard = LoginGuard::new(app_state, &pending.account, client);
+        if let Err(e) = guard.check().await {
+            let _ = app_state.del(&key).await;
+            return Err(e);
         }
-        let user = filter_users[0].clone();
-        // 获取用户角色
-        let roles = user.find_related(Role).all(&app_state.db.connection).await?;
-        // 获取用户菜单权限
-        let menus = roles.load_many_to_many(Menu, RoleMenu, &app_state.db.connection).await?;
-        let roles = roles.iter().map(|role| {
-            role.value.clone()
-        }).collect();
-        // 菜单权限去重
-        let menus = Utils::dedup(menus, |menu| menu.value.clone()).await;
-        if user.password != login.password {
-            return Err(AppError::Other("账号或密码错误"));
+        let user = app_state
+            .find_by_id::<User>(pending.uid)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Relogin("两步验证已过期，请重新登录"))?;
+        if let Some(reason) = user.inactive_reason(Utc::now()) {
+            let _ = app_state.del(&key).await;
+            guard.record(Some(user.uid), false, reason).await;
+            return Err(AppError::Other(reason));
         }
-        let mut login_user = LoginUserBuilder::default()
+        if !MfaUtils::verify(app_state, &user, &body.code).await? {
+            guard.record(Some(user.uid), false, "动态验证码错误").await;
+            if let Err(e) = guard.fail().await {
+                let _ = app_state.del(&key).await;
+                return Err(e);
+            }
+            return Err(AppError::Other("动态验证码错误"));
+        }
+        app_state.del(&key).await?;
+        guard.succeed().await;
+        let token_pair = Self::sign_in(app_state, &user, client).await?;
+        guard.record(Some(user.uid), true, "登录成功").await;
+        Ok(token_pair)
+    }
+
+    /// 创建登录会话并签发令牌
+    async fn sign_in(app_state: &AppState, user: &user::Model, client: &ClientInfo) -> Result<TokenPair, AppError> {
+        // 角色及权限不写入令牌，授权时从权限缓存获取，变更后即时生效
+        let login_user = LoginUserBuilder::default()
             .uid(user.uid)
             .account(Some(user.account.clone()))
             .name(user.name.clone())
@@ -80,30 +182,72 @@ impl TokenHandler {
             .create_time(Some(user.create_time))
             .updater_id(user.updater_id)
             .update_time(user.update_time)
-            .perms(Some(menus))
-            .roles(Some(roles))
             .build()?;
-        let token = JwtUtils::create(login_user.clone(), app_state.env.jwt_secret.as_str(), 36000)
-            .or_else(|e| {
-                Err(AppError::Other("生成令牌失败"))
-            })?;
-        login_user.token = Some(token.to_string());
+        // 每次登录生成新的会话，会话id同时作为令牌族id
+        let now = Utc::now();
+        let session = SessionInfo {
+            sid: uuid::Uuid::new_v4().simple().to_string(),
+            uid: user.uid,
+            device: client.device.clone(),
+            ip: client.ip.clone(),
+            user_agent: client.user_agent.clone(),
+            login_time: now,
+            last_seen: now,
+            login_user,
+        };
+        Self::issue(app_state, session).await
+    }
+
+    /// 使用刷新令牌换取新的令牌。刷新令牌每次使用后轮换，旧令牌再次使用时撤销整个令牌族
+    pub async fn refresh(
+        Extension(app_state): Extension<Arc<AppState>>,
+        AppJson(refresh): AppJson<RefreshBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        // 在刷新令牌所属的租户下轮换，不依赖请求头中的租户
+        let tenant_id = JwtUtils::token_tenant(&refresh.refresh_token)
+            .ok_or(AppError::Relogin("刷新令牌无效或已过期"))?;
+        let tenant_id = TenantUtils::check(&app_state, tenant_id).await?;
+        let token_pair = TenantUtils::scope(tenant_id, Self::rotate(&app_state, &refresh.refresh_token)).await?;
+        Ok(R::ok(token_pair))
+    }
+
+    async fn rotate(app_state: &AppState, refresh_token: &str) -> Result<TokenPair, AppError> {
+        let hash = JwtUtils::hash_refresh_token(refresh_token);
+        let key = RedisKey::refresh_token(&hash);
         app_state
-            .set_ex(format!("{}:{}:{}", APP_NAME, LOGIN_UID, login_user.uid), login_user, 36000)
-            .await?;
-        transaction.commit().await?;
-        Ok(R::ok(token))
+            .exists(&key)
+            .await
+            .map_err(|e| AppError::Relogin("刷新令牌无效或已过期"))?;
+        let mut record = app_state.cached::<RefreshRecord>(&key).await?;
+        // 取出并删除令牌族当前的令牌哈希以原子地领取本次轮换，并发使用同一令牌时只有一个请求能领取成功
+        let current = match record.used {
+            true => None,
+            false => app_state
+                .get_del::<_, String>(RedisKey::refresh_family(&record.family))
+                .await?,
+        };
+        if current.as_deref() != Some(hash.as_str()) {
+            tracing::warn!("刷新令牌重复使用，撤销令牌族：{}，用户：{}", record.family, record.uid);
+            SessionUtils::remove(app_state, record.uid, &record.family).await?;
+            return Err(AppError::Relogin("刷新令牌已失效，请重新登录"));
+        }
+        // 轮换：旧令牌标记为已使用，保留至过期用于检测重复使用
+        record.used = true;
+        app_state.set_keep_ttl(&key, record.clone()).await?;
+        let mut session = SessionUtils::get(app_state, &record.family)
+            .await?
+            .ok_or(AppError::Relogin("登录已失效，请重新登录"))?;
+        SessionUtils::check_account(app_state, &session).await?;
+        session.last_seen = Utc::now();
+        Self::issue(app_state, session).await
     }
 
     pub async fn logout(
         Extension(app_state): Extension<Arc<AppState>>,
         Extension(request_state): Extension<Arc<RequestState>>,
     ) -> Result<impl IntoResponse, AppError> {
-        let transaction = app_state.begin().await?;
-        app_state
-            .del(format!("{}:{}:{}", APP_NAME, LOGIN_UID, request_state.login_user.uid))
-            .await?;
-        transaction.commit().await?;
+        // 仅退出当前会话，会话的刷新令牌同时失效
+        SessionUtils::remove(&app_state, request_state.login_user.uid, &request_state.jti).await?;
         Ok(R::ok(true))
     }
 
@@ -111,12 +255,49 @@ impl TokenHandler {
         Extension(app_state): Extension<Arc<AppState>>,
         Extension(request_state): Extension<Arc<RequestState>>,
     ) -> Result<impl IntoResponse, AppError> {
-        app_state
-            .exists(format!("{}:{}:{}", APP_NAME, LOGIN_UID, request_state.login_user.uid))
-            .await.map_err(|e| {
-            AppError::Relogin("登录已失效")
-        })?;
+        SessionUtils::get(&app_state, &request_state.jti)
+            .await?
+            .ok_or(AppError::Relogin("登录已失效"))?;
         Ok(R::ok(true))
     }
 
+    /// 公开验签公钥，供下游服务自行校验令牌
+    pub async fn jwks(Extension(app_state): Extension<Arc<AppState>>) -> impl IntoResponse {
+        Json(app_state.jwt.jwks.clone())
+    }
+
+    /// 签发访问令牌及刷新令牌，并按刷新令牌有效期延长登录会话
+    async fn issue(app_state: &AppState, session: SessionInfo) -> Result<TokenPair, AppError> {
+        let env = &app_state.env;
+        let access_token = JwtUtils::create(
+            session.login_user.clone(),
+            &session.sid,
+            &app_state.jwt,
+            env.access_token_expire,
+        )
+        .map_err(|e| AppError::Other("生成令牌失败"))?;
+        let refresh_token = JwtUtils::refresh_token();
+        let hash = JwtUtils::hash_refresh_token(&refresh_token);
+        let expire = env.refresh_token_expire as u64;
+        let record = RefreshRecord {
+            uid: session.uid,
+            family: session.sid.clone(),
+            used: false,
+        };
+        app_state
+            .set_ex(RedisKey::refresh_token(&hash), record, expire)
+            .await?;
+        app_state
+            .set_ex(RedisKey::refresh_family(&session.sid), hash, expire)
+            .await?;
+        SessionUtils::save(app_state, &session).await?;
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: env.access_token_expire,
+            refresh_expires_in: env.refresh_token_expire,
+        })
+    }
+
 }
@@ -1,15 +1,39 @@
-use crate::entity::prelude::{Menu, Role, RoleMenu, User, UserRole};
-use crate::entity::user::{AuthRoleBody, LoginUserBuilder, UserQuery};
-use crate::entity::{user_role};
+use crate::entity::menu::{MenuType, RouteVo};
+use crate::entity::prelude::{File, Menu, Role, User, UserRole, UserStatusLog};
+use crate::entity::user::{
+    AccountState, AuthRoleBody, LoginUserBuilder, PasswordBody, ProfileBody, UserQuery, UserStatus,
+    UserStatusBody, UserStatusLogQuery,
+};
+use crate::entity::{menu, role, user, user_role, user_status_log};
 use crate::core::error::AppError;
-use crate::core::result::{AppJson, AppQuery, R};
+use crate::core::page::{PageQuery, PageSelect};
+use crate::core::result::{AppJson, AppQuery, ClientInfo, R};
+use axum::extract::Multipart;
 use axum::response::IntoResponse;
 use axum::Extension;
 use rato_core::database::DbPool;
-use sea_orm::{ColumnTrait, EntityTrait, LoaderTrait, ModelTrait, QueryFilter, Set};
+use rato_core::oper_log::OperContext;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, QueryTrait, Set,
+};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use validator::Validate;
 use crate::state::{AppState, RequestState};
-use crate::utils::Utils;
+use crate::utils::tenant::TenantUtils;
+use crate::utils::auth::AuthUtils;
+use crate::utils::file::FileUtils;
+use crate::utils::login::LoginGuard;
+use crate::utils::password::{PasswordUtils, PasswordVerify};
+use crate::utils::perm::PermUtils;
+use crate::utils::scope::ScopeSelect;
+use crate::utils::session::SessionUtils;
+use crate::utils::tree::TreeUtils;
+
+// 头像最大2M
+const AVATAR_MAX_SIZE: usize = 2 * 1024 * 1024;
 
 /// 用户handler
 pub struct UserHandler;
@@ -20,23 +44,13 @@ impl UserHandler {
         Extension(app_state): Extension<Arc<AppState>>,
         Extension(request_state): Extension<Arc<RequestState>>,
     ) -> Result<impl IntoResponse, AppError> {
-        let user = User::find_by_id(request_state.login_user.uid)
+        let user = app_state.find_by_id::<User>(request_state.login_user.uid)
             .one(&app_state.db.connection)
             .await?.ok_or_else(||{
             AppError::Other("未找到用户信息")
         })?;
-        // 获取用户角色
-        let roles = user
-            .find_related(Role)
-            .all(&app_state.db.connection)
-            .await?;
-        // 获取用户菜单权限
-        let menus = roles
-            .load_many_to_many(Menu, RoleMenu, &app_state.db.connection)
-            .await?;
-        let roles = roles.iter().map(|role| role.value.clone()).collect();
-        // 去重
-        let menus = Utils::dedup(menus, |menu| menu.value.clone()).await;
+        // 获取用户角色及菜单权限
+        let user_perm = PermUtils::resolve(&app_state, user.uid).await?;
         Ok(R::ok(
             LoginUserBuilder::default()
                 .uid(user.uid)
@@ -47,21 +61,73 @@ impl UserHandler {
                 .create_time(Some(user.create_time))
                 .updater_id(user.updater_id)
                 .update_time(user.update_time)
-                .perms(Some(menus))
-                .roles(Some(roles))
+                .perms(Some(user_perm.perms))
+                .roles(Some(user_perm.roles))
                 .build()?,
         ))
     }
 
+    /// 当前用户可访问的前端路由。包含有权限的菜单及其上级菜单，不包含按钮
+    pub async fn routes(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        context: Option<Extension<OperContext>>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let login_user = &request_state.login_user;
+        let super_admin = PermUtils::is_super_admin(&app_state, login_user);
+        let menus = app_state.find::<Menu>()
+            .filter(menu::Column::Type.eq(MenuType::Menu.to_string()))
+            .order_by_asc(menu::Column::Sort)
+            .order_by_asc(menu::Column::Uid)
+            .all(&app_state.db.connection)
+            .await?;
+        let parents = menus
+            .iter()
+            .map(|menu| (menu.uid, menu.parent_id))
+            .collect::<HashMap<_, _>>();
+        let mut allowed = HashSet::new();
+        let mut bypassed = 0;
+        for menu in menus.iter() {
+            if !login_user.has_any_perm(vec![menu.value.clone()]) {
+                if !super_admin {
+                    continue;
+                }
+                bypassed += 1;
+            }
+            // 补全上级菜单，保证路由树连通
+            let mut current = Some(menu.uid);
+            while let Some(uid) = current.filter(|uid| allowed.insert(*uid)) {
+                current = parents.get(&uid).copied();
+            }
+        }
+        if bypassed > 0 {
+            PermUtils::audit_bypass(
+                context.as_ref().map(|Extension(context)| context),
+                login_user,
+                format_args!("获取前端路由，包含{}个未授权的菜单", bypassed),
+            );
+        }
+        let menus = menus
+            .into_iter()
+            .filter(|menu| allowed.contains(&menu.uid))
+            .collect::<Vec<_>>();
+        let routes = TreeUtils::build(menus, |menu| menu.uid, |menu| menu.parent_id)
+            .into_iter()
+            .map(RouteVo::from)
+            .collect::<Vec<_>>();
+        Ok(R::ok(routes))
+    }
+
     pub async fn info(
         Extension(app_state): Extension<Arc<AppState>>,
-        Extension(_request_state): Extension<Arc<RequestState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppQuery(user): AppQuery<UserQuery>,
     ) -> Result<impl IntoResponse, AppError> {
         if user.uid.is_none() {
             return Err(AppError::Other("uid不能为空"));
         }
-        let user = User::find_by_id(user.uid.unwrap())
+        let user = app_state.find_by_id::<User>(user.uid.unwrap())
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?.ok_or_else(||{
             AppError::Other("未找到用户信息")
@@ -69,29 +135,80 @@ impl UserHandler {
         Ok(R::ok(user))
     }
 
+    /// 分页查询用户
+    pub async fn list(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppQuery(user): AppQuery<UserQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut select = app_state.find::<User>().scoped(&request_state.data_scope);
+        if let Some(uid) = user.uid {
+            select = select.filter(user::Column::Uid.eq(uid));
+        }
+        if let Some(dept_id) = user.dept_id {
+            select = select.filter(user::Column::DeptId.eq(dept_id));
+        }
+        let select = select
+            .filter_text(user::Column::Account, user.account, page.fuzzy)
+            .filter_text(user::Column::Name, user.name, page.fuzzy)
+            .filter_create_time(user::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[
+                    ("uid", user::Column::Uid),
+                    ("account", user::Column::Account),
+                    ("name", user::Column::Name),
+                    ("create_time", user::Column::CreateTime),
+                    ("update_time", user::Column::UpdateTime),
+                ],
+                user::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
+
     pub async fn auth_role(
         Extension(app_state): Extension<Arc<AppState>>,
-        Extension(_request_state): Extension<Arc<RequestState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
         AppJson(auth_role): AppJson<AuthRoleBody>,
     ) -> Result<impl IntoResponse, AppError> {
-        let user = User::find_by_id(auth_role.user_id)
+        let user = app_state.find_by_id::<User>(auth_role.user_id)
+            .scoped(&request_state.data_scope)
             .one(&app_state.db.connection)
             .await?.ok_or_else(||{
             AppError::Other("未找到用户信息")
         })?;
+        // 只能分配当前租户且在调用者数据范围内的角色
+        let role_uids = auth_role.role_uids.iter().collect::<HashSet<_>>();
+        let count = app_state.find::<Role>()
+            .scoped(&request_state.data_scope)
+            .filter(role::Column::Uid.is_in(role_uids.iter().copied().copied()))
+            .count(&app_state.db.connection)
+            .await?;
+        if count as usize != role_uids.len() {
+            return Err(AppError::Other("未找到角色信息"));
+        }
+        // 只替换调用者数据范围内的角色，范围外的已有角色保持不变
+        let scoped_roles = app_state.find::<Role>()
+            .scoped(&request_state.data_scope)
+            .select_only()
+            .column(role::Column::Uid)
+            .into_query();
         let transaction = app_state.begin().await?;
-        UserRole::delete_many()
+        app_state.delete_many::<UserRole>()
             .filter(user_role::Column::UserId.eq(user.uid))
+            .filter(user_role::Column::RoleId.in_subquery(scoped_roles))
             .exec(&transaction)
             .await
-            .or_else(|e| {
+            .map_err(|e| {
                 tracing::error!("{:?}", e);
-                Err(AppError::Other("清空用户角色失败"))
+                AppError::Other("清空用户角色失败")
             })?;
         let models = auth_role
             .role_uids
             .iter()
             .map(|role_id| user_role::ActiveModel {
+                tenant_id: Set(TenantUtils::current()),
                 user_id: Set(user.uid),
                 role_id: Set(*role_id),
             })
@@ -99,11 +216,221 @@ impl UserHandler {
         UserRole::insert_many(models)
             .exec(&transaction)
             .await
-            .or_else(|e| {
+            .map_err(|e| {
                 tracing::error!("{:?}", e);
-                Err(AppError::Other("分配角色失败"))
+                AppError::Other("分配角色失败")
             })?;
         transaction.commit().await?;
+        PermUtils::invalidate(&app_state).await?;
         Ok(R::ok(auth_role.role_uids.len()))
     }
+
+    /// 修改本人密码。原密码校验通过后撤销当前会话外的全部会话，原密码错误与登录失败共用失败次数及锁定
+    pub async fn password(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        client: ClientInfo,
+        AppJson(body): AppJson<PasswordBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        body.validate()?;
+        let user = app_state.find_by_id::<User>(request_state.login_user.uid)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))?;
+        let guard = LoginGuard::new(&app_state, &user.account, &client);
+        guard.check().await?;
+        let password_config = app_state.env.password_config();
+        if let PasswordVerify::Invalid = PasswordUtils::verify_async(&body.old_password, Some(&user.password), &password_config).await {
+            guard.fail().await?;
+            return Err(AppError::Other("原密码错误"));
+        }
+        guard.succeed().await;
+        if body.old_password == body.new_password {
+            return Err(AppError::Other("新密码不能与原密码相同"));
+        }
+        // 只更新变更的字段，不覆盖并发修改的其他字段
+        user::ActiveModel {
+            uid: Set(user.uid),
+            password: Set(PasswordUtils::hash_async(&body.new_password, &password_config).await?),
+            updater_id: Set(Some(user.uid)),
+            update_time: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .update(&app_state.db.connection)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("修改密码失败")
+        })?;
+        let count = SessionUtils::remove_others(&app_state, user.uid, &request_state.jti).await?;
+        tracing::info!(target: "audit", "用户{}修改密码，撤销{}个其他会话", user.uid, count);
+        Ok(R::ok(true))
+    }
+
+    /// 修改本人资料
+    pub async fn profile(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<ProfileBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let body = ProfileBody {
+            name: body.name.trim().to_string(),
+            phone: body.phone.map(|phone| phone.trim().to_string()).filter(|phone| !phone.is_empty()),
+            email: body.email.map(|email| email.trim().to_string()).filter(|email| !email.is_empty()),
+        };
+        body.validate()?;
+        let user = app_state.find_by_id::<User>(request_state.login_user.uid)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))?;
+        let user = user::ActiveModel {
+            uid: Set(user.uid),
+            name: Set(body.name),
+            phone: Set(body.phone),
+            email: Set(body.email),
+            updater_id: Set(Some(user.uid)),
+            update_time: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .update(&app_state.db.connection)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("修改资料失败")
+        })?;
+        Ok(R::ok(user))
+    }
+
+    /// 上传本人头像，multipart表单的file字段。原头像文件一并删除
+    pub async fn avatar(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        mut multipart: Multipart,
+    ) -> Result<impl IntoResponse, AppError> {
+        let upload = FileUtils::upload(&mut multipart).await?;
+        if upload.bytes.len() > AVATAR_MAX_SIZE {
+            return Err(AppError::Other("头像大小不能超过2M"));
+        }
+        let uid = request_state.login_user.uid;
+        let file = FileUtils::save_image(&app_state, uid, &upload).await?;
+        // 锁定用户行后读取原头像，并发上传时各自删除的原头像不会重复
+        let transaction = app_state.begin().await?;
+        let user = app_state.find_by_id::<User>(uid)
+            .lock_exclusive()
+            .one(&transaction)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))?;
+        user::ActiveModel {
+            uid: Set(user.uid),
+            avatar: Set(Some(file.uid.clone())),
+            updater_id: Set(Some(user.uid)),
+            update_time: Set(Some(Utc::now())),
+            ..Default::default()
+        }
+        .update(&transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("更新头像失败")
+        })?;
+        transaction.commit().await?;
+        let old_avatar = user.avatar;
+        let old_avatar = match old_avatar {
+            Some(old_avatar) => app_state.find_by_id::<File>(old_avatar).one(&app_state.db.connection).await?,
+            None => None,
+        };
+        if let Some(old_avatar) = old_avatar {
+            FileUtils::remove(&app_state, old_avatar).await?;
+        }
+        Ok(R::ok(file))
+    }
+
+    /// 修改账号状态。停用或锁定后立即撤销用户的全部会话，每次变更均记录操作人及原因
+    pub async fn status(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(request_state): Extension<Arc<RequestState>>,
+        AppJson(body): AppJson<UserStatusBody>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let status = UserStatus::from_str(&body.status)
+            .ok_or(AppError::Other("账号状态只能为active或disabled"))?;
+        if body.reason.trim().is_empty() {
+            return Err(AppError::Other("变更原因不能为空"));
+        }
+        let operator_id = request_state.login_user.uid;
+        if body.user_id == operator_id {
+            return Err(AppError::Other("不能修改自己的账号状态"));
+        }
+        let user = app_state.find_by_id::<User>(body.user_id)
+            .scoped(&request_state.data_scope)
+            .one(&app_state.db.connection)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))?;
+        let now = Utc::now();
+        let transaction = app_state.begin().await?;
+        // 只更新状态相关的字段，不覆盖并发修改的其他字段
+        let user = user::ActiveModel {
+            uid: Set(user.uid),
+            status: Set(status.to_string()),
+            locked_until: Set(body.locked_until),
+            expire_time: Set(body.expire_time),
+            updater_id: Set(Some(operator_id)),
+            update_time: Set(Some(now)),
+            ..Default::default()
+        }
+        .update(&transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("修改账号状态失败")
+        })?;
+        user_status_log::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
+            user_id: Set(user.uid),
+            status: Set(user.status.clone()),
+            locked_until: Set(user.locked_until),
+            expire_time: Set(user.expire_time),
+            reason: Set(body.reason.clone()),
+            operator_id: Set(operator_id),
+            create_time: Set(now),
+            ..Default::default()
+        }
+        .insert(&transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("记录账号状态变更失败")
+        })?;
+        transaction.commit().await?;
+        // 先更新状态缓存，撤销会话期间仍在处理的请求也按新状态校验
+        SessionUtils::set_account(&app_state, user.uid, AccountState::from(&user)).await?;
+        if let Some(reason) = user.inactive_reason(now) {
+            let count = SessionUtils::remove_all(&app_state, user.uid).await?;
+            tracing::info!(target: "audit", "{}，撤销用户{}的{}个会话，操作人：{}", reason, user.uid, count, operator_id);
+        }
+        Ok(R::ok(user))
+    }
+
+    /// 分页查询账号状态变更记录
+    pub async fn status_log(
+        Extension(app_state): Extension<Arc<AppState>>,
+        Extension(_request_state): Extension<Arc<RequestState>>,
+        AppQuery(log): AppQuery<UserStatusLogQuery>,
+        AppQuery(page): AppQuery<PageQuery>,
+    ) -> Result<impl IntoResponse, AppError> {
+        let mut select = app_state.find::<UserStatusLog>();
+        if let Some(user_id) = log.user_id {
+            select = select.filter(user_status_log::Column::UserId.eq(user_id));
+        }
+        if let Some(operator_id) = log.operator_id {
+            select = select.filter(user_status_log::Column::OperatorId.eq(operator_id));
+        }
+        let select = select
+            .filter_create_time(user_status_log::Column::CreateTime, &page)
+            .sort_by(
+                &page,
+                &[("create_time", user_status_log::Column::CreateTime)],
+                user_status_log::Column::CreateTime,
+            )?;
+        Ok(R::ok(page.fetch(select, &app_state.db.connection).await?))
+    }
 }
@@ -8,13 +8,17 @@ mod entity;
 mod config;
 
 use dotenv::dotenv;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::signal;
 use tracing_subscriber::filter::LevelFilter;
-use crate::config::{DbConfig, GlobalConfig, RedisConfig};
+use crate::config::{DbConfig, GlobalConfig, JwtConfig, RedisConfig};
 use crate::router::AppRouter;
 use crate::state::AppState;
+use crate::utils::password::PasswordUtils;
+use crate::utils::upload::ChunkUtils;
 
 /// 入口函数
 #[tokio::main]
@@ -26,18 +30,28 @@ async fn main() {
     // 环境变量解析
     dotenv().ok();
     let config = GlobalConfig::init();
+    PasswordUtils::init(&config.password_config()).expect("密码哈希配置错误");
     let server = format!("{}:{}", &config.server_host, &config.server_port);
     tracing::info!("Server starting at {}", server);
     // 监听ip及端口
     let listener = TcpListener::bind(server).await.unwrap();
+    let app_state = Arc::new(AppState::new(
+        config.clone(),
+        DbConfig::init(&config.database_url).await,
+        RedisConfig::init(&config.redis_url).await,
+        JwtConfig::init(&config),
+    ));
+    // 定期清理过期的分片上传
+    tokio::spawn(ChunkUtils::gc(
+        Path::new(&config.upload_dir).to_path_buf(),
+        config.upload_expire,
+    ));
     // 初始化路由注册并启动
     axum::serve(
         listener,
-        AppRouter::init(Arc::new(AppState::new(
-            config.clone(),
-            DbConfig::init(&config.database_url).await,
-            RedisConfig::init(&config.redis_url).await,
-        ))),
+        AppRouter::init(app_state)
+        // 保留连接地址，用于获取客户端ip
+        .into_make_service_with_connect_info::<SocketAddr>(),
     )
     .with_graceful_shutdown(shutdown_signal())
     .await
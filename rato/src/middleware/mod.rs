@@ -1,20 +1,26 @@
-use crate::core::constant::{APP_NAME, LOGIN_UID};
 use crate::core::result::R;
+use crate::core::error::AppError;
 use crate::entity::menu::RequirePermission;
 use crate::entity::role::RequireRole;
 use crate::state::{AppState, RequestState};
 use crate::utils::auth::AuthUtils;
 use crate::utils::jwt::JwtUtils;
+use crate::utils::perm::PermUtils;
+use crate::utils::scope::DataScope;
+use crate::utils::session::SessionUtils;
+use crate::utils::tenant::TenantUtils;
 use axum::extract::Request;
+use axum::middleware::Next;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::sync::Arc;
 use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use rato_core::authenticator::Authenticator;
-use rato_core::authorizer::Authorizer;
-use rato_core::error_handler::ErrorHandler;
-use rato_core::redis::RedisPool;
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+use rato_core::authenticator::AsyncAuthenticator;
+use rato_core::authorizer::AsyncAuthorizer;
+use rato_core::error_handler::{ErrorHandler, PanicContext};
+use rato_core::oper_log::OperContext;
 
 #[derive(Deserialize, PartialEq, Debug, Serialize, Clone, Default)]
 pub enum CheckType {
@@ -63,60 +69,150 @@ impl AuthState {
 }
 
 /// 自定义认证实现
-impl Authenticator for AuthState {
+impl<Body> AsyncAuthenticator<Body> for AuthState
+where
+    Body: Send + 'static,
+{
     type Err = R<String>;
+    type Future = BoxFuture<'static, Result<Request<Body>, Self::Err>>;
 
-    fn authenticate<Body>(&self, req: &mut Request<Body>) -> Result<(), Self::Err> {
-        let token = req
-            .headers()
-            .get("Authorization")
-            .and_then(|header| header.to_str().ok())
-            .and_then(|header| {
-                if header.starts_with("Bearer ") {
-                    Some(header[7..].to_owned())
-                } else {
-                    None
-                }
-            })
-            .ok_or_else(|| R::fail("未找到令牌"))?;
-        let app_state = req.extensions_mut().get::<Arc<AppState>>().unwrap();
-        let result = JwtUtils::decode(token.as_str(), app_state.env.jwt_secret.as_str());
-        let mut login_user = match result {
-            Ok(claims) => claims.login_user,
-            Err(e) => return Err(R::fail(format!("{}", e).as_str())),
-        };
-        login_user.token = Some(token);
-        let future = async {
-            app_state
-                .exists(format!("{}:{}:{}", APP_NAME, LOGIN_UID, login_user.uid))
+    fn authenticate(&self, mut req: Request<Body>) -> Self::Future {
+        Box::pin(async move {
+            let token = req
+                .headers()
+                .get("Authorization")
+                .and_then(|header| header.to_str().ok())
+                .and_then(|header| header.strip_prefix("Bearer "))
+                .map(|token| token.to_owned())
+                .ok_or_else(|| R::fail("未找到令牌"))?;
+            let app_state = req.extensions().get::<Arc<AppState>>().unwrap().clone();
+            let result = JwtUtils::decode(token.as_str(), &app_state.jwt);
+            let claims = match result {
+                Ok(claims) => claims,
+                Err(e) => return Err(R::fail(format!("{}", e).as_str())),
+            };
+            let mut login_user = claims.login_user;
+            login_user.token = Some(token);
+            // 校验令牌所属会话，会话被撤销后令牌随即失效
+            let session = SessionUtils::get(&app_state, &claims.jti)
                 .await
-                .map_err(|_| return R::new(false, 401, None, "登录已失效，请重新登录"))
-        };
-        tokio::task::block_in_place(move || tokio::runtime::Handle::current().block_on(future))?;
-        req.extensions_mut()
-            .insert(Arc::new(RequestState { login_user }));
-        Ok(())
+                .ok()
+                .flatten()
+                .filter(|session| session.uid == login_user.uid)
+                .ok_or_else(|| R::new(false, 401, None, "登录已失效，请重新登录"))?;
+            // 逐请求校验账号状态，停用、锁定或过期后会话随即失效
+            match SessionUtils::check_account(&app_state, &session).await {
+                Ok(_) => {}
+                Err(AppError::Relogin(reason)) => return Err(R::new(false, 401, None, reason)),
+                Err(e) => {
+                    tracing::error!("校验账号状态失败：{:?}", e);
+                    return Err(R::fail("校验账号状态失败"));
+                }
+            }
+            if let Err(e) = SessionUtils::touch(&app_state, session).await {
+                tracing::error!("更新会话活跃时间失败：{:?}", e);
+            }
+            // 补充操作日志的操作人
+            if let Some(context) = req.extensions().get::<OperContext>() {
+                context.user(login_user.uid, login_user.account.clone());
+            }
+            req.extensions_mut().insert(Arc::new(RequestState {
+                data_scope: DataScope {
+                    uid: login_user.uid,
+                    own: true,
+                    ..Default::default()
+                },
+                login_user,
+                jti: claims.jti,
+            }));
+            Ok(req)
+        })
     }
 }
 
-/// 自定义授权实现
-impl Authorizer for AuthState {
+/// 自定义授权实现。角色及权限从权限缓存获取，不使用令牌中的快照
+impl<Body> AsyncAuthorizer<Body> for AuthState
+where
+    Body: Send + 'static,
+{
     type Err = R<String>;
+    type Future = BoxFuture<'static, Result<Request<Body>, Self::Err>>;
 
-    fn authorize<Body>(&self, req: &mut Request<Body>) -> Result<(), Self::Err> {
-        let request_state = req.extensions_mut().get::<Arc<RequestState>>().unwrap();
-        if request_state.login_user.uid != 1 {
-            if let false = request_state.login_user.auth(&self) {
+    fn authorize(&self, mut req: Request<Body>) -> Self::Future {
+        let auth_state = self.clone();
+        // 补充操作日志的权限要求
+        if let (Some(context), Some(perm)) = (req.extensions().get::<OperContext>(), &auth_state.perm) {
+            context.perm(perm.perms.join(","));
+        }
+        Box::pin(async move {
+            let app_state = req.extensions().get::<Arc<AppState>>().unwrap().clone();
+            let request_state = req.extensions().get::<Arc<RequestState>>().unwrap().clone();
+            let user_perm = PermUtils::resolve(&app_state, request_state.login_user.uid)
+                .await
+                .map_err(|e| {
+                    tracing::error!("获取用户权限失败：{:?}", e);
+                    R::new(false, 500, None, "获取用户权限失败")
+                })?;
+            let mut login_user = request_state.login_user.clone();
+            login_user.matcher = Some(PermUtils::matcher(&app_state, login_user.uid, &user_perm));
+            login_user.roles = Some(user_perm.roles);
+            login_user.perms = Some(user_perm.perms);
+            let mut data_scope = user_perm.data_scope;
+            let allowed = login_user.auth(&auth_state);
+            if PermUtils::is_super_admin(&app_state, &login_user) {
+                // 超级管理员本身不具备的权限或数据范围视为跳过校验，记录审计日志
+                if !allowed || !data_scope.all {
+                    PermUtils::audit_bypass(
+                        req.extensions().get::<OperContext>(),
+                        &login_user,
+                        format_args!(
+                            "请求：{} {}，权限要求：{:?}，角色要求：{:?}",
+                            req.method(),
+                            req.uri(),
+                            auth_state.perm,
+                            auth_state.role
+                        ),
+                    );
+                }
+                data_scope = DataScope::all(login_user.uid);
+            } else if !allowed {
                 return Err(R::fail("权限不足"));
             }
-        }
-        Ok(())
+            // 后续handler获取到的为最新的角色及权限
+            req.extensions_mut().insert(Arc::new(RequestState {
+                login_user,
+                jti: request_state.jti.clone(),
+                data_scope,
+            }));
+            Ok(req)
+        })
+    }
+}
+
+/// 租户中间件。解析请求所属租户，后续的认证、授权及handler均在该租户下执行
+pub async fn tenant(req: Request, next: Next) -> Response {
+    let app_state = req.extensions().get::<Arc<AppState>>().unwrap().clone();
+    match TenantUtils::resolve(&app_state, req.headers()).await {
+        Ok(tenant_id) => TenantUtils::scope(tenant_id, next.run(req)).await,
+        Err(e) => e.into_response(),
     }
 }
 
-/// 自定义全局异常返回
+/// 自定义全局异常返回，返回请求id便于排查
 impl ErrorHandler for AuthState {
-    fn msg(&self) -> impl IntoResponse {
-        R::<String>::new(false, StatusCode::INTERNAL_SERVER_ERROR.as_u16() as u32, None, "服务器异常")
+    fn msg(&self, context: PanicContext) -> impl IntoResponse {
+        tracing::error!(
+            "请求异常：{} {}，请求id：{}，异常信息：{}",
+            context.method,
+            context.uri,
+            context.request_id,
+            context.message()
+        );
+        R::new(
+            false,
+            StatusCode::INTERNAL_SERVER_ERROR.as_u16() as u32,
+            Some(context.request_id),
+            "服务器异常",
+        )
     }
 }
\ No newline at end of file
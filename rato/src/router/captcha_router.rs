@@ -0,0 +1,12 @@
+use crate::handler::captcha_handler::CaptchaHandler;
+use axum::routing::get;
+use axum::Router;
+
+pub struct CaptchaRouter;
+
+/// 验证码路由，无需登录
+impl CaptchaRouter {
+    pub fn init() -> Router {
+        Router::new().route("/captcha", get(CaptchaHandler::create))
+    }
+}
@@ -0,0 +1,46 @@
+use crate::handler::dept_handler::DeptHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::{get, post};
+use axum::Router;
+
+pub struct DeptRouter;
+
+/// 部门路由
+impl DeptRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/dept",
+                Router::new()
+                    .route(
+                        "/add",
+                        post(DeptHandler::add).layer(require_any_perm!("dept:add")),
+                    )
+                    .route(
+                        "/remove",
+                        post(DeptHandler::remove).layer(require_any_perm!("dept:remove")),
+                    )
+                    .route(
+                        "/edit",
+                        post(DeptHandler::edit).layer(require_any_perm!("dept:edit")),
+                    )
+                    .route(
+                        "/info",
+                        get(DeptHandler::info).layer(require_any_perm!("dept:info")),
+                    )
+                    .route(
+                        "/tree",
+                        get(DeptHandler::tree).layer(require_any_perm!("dept:tree")),
+                    )
+                    .route(
+                        "/list",
+                        get(DeptHandler::list).layer(require_any_perm!("dept:list")),
+                    )
+                    .route(
+                        "/assign_user",
+                        post(DeptHandler::assign_user).layer(require_any_perm!("dept:assign_user")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
@@ -0,0 +1,46 @@
+use crate::handler::file_handler::FileHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::{get, post, put};
+use axum::Router;
+
+pub struct FileRouter;
+
+/// 文件路由
+impl FileRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/file",
+                Router::new()
+                    .route(
+                        "/upload",
+                        post(FileHandler::upload).layer(require_any_perm!("file:upload")),
+                    )
+                    .route(
+                        "/chunk",
+                        post(FileHandler::chunk_init).layer(require_any_perm!("file:upload")),
+                    )
+                    .route(
+                        "/chunk/{upload_id}",
+                        get(FileHandler::chunk_status).layer(require_any_perm!("file:upload")),
+                    )
+                    .route(
+                        "/chunk/{upload_id}/complete",
+                        post(FileHandler::chunk_complete).layer(require_any_perm!("file:upload")),
+                    )
+                    .route(
+                        "/chunk/{upload_id}/{index}",
+                        put(FileHandler::chunk_put).layer(require_any_perm!("file:upload")),
+                    )
+                    .route(
+                        "/remove",
+                        post(FileHandler::remove).layer(require_any_perm!("file:remove")),
+                    )
+                    .route(
+                        "/{uid}",
+                        get(FileHandler::download).layer(require_any_perm!("file:download")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
@@ -0,0 +1,26 @@
+use crate::handler::login_handler::LoginHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::{get, post};
+use axum::Router;
+
+pub struct LoginRouter;
+
+/// 登录日志路由
+impl LoginRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/login",
+                Router::new()
+                    .route(
+                        "/log",
+                        get(LoginHandler::list).layer(require_any_perm!("login:log")),
+                    )
+                    .route(
+                        "/unlock",
+                        post(LoginHandler::unlock).layer(require_any_perm!("login:unlock")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
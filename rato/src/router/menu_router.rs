@@ -27,6 +27,14 @@ impl MenuRouter {
                     .route(
                         "/info",
                         get(MenuHandler::info).layer(require_any_perm!("menu:info")),
+                    )
+                    .route(
+                        "/tree",
+                        get(MenuHandler::tree).layer(require_any_perm!("menu:tree")),
+                    )
+                    .route(
+                        "/list",
+                        get(MenuHandler::list).layer(require_any_perm!("menu:list")),
                     ),
             )
             .layer(require_token!())
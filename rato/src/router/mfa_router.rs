@@ -0,0 +1,30 @@
+use crate::handler::mfa_handler::MfaHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::post;
+use axum::Router;
+
+pub struct MfaRouter;
+
+/// 两步验证路由
+impl MfaRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/mfa",
+                Router::new()
+                    .route(
+                        "/enroll",
+                        post(MfaHandler::enroll).layer(require_any_perm!("mfa:enroll")),
+                    )
+                    .route(
+                        "/confirm",
+                        post(MfaHandler::confirm).layer(require_any_perm!("mfa:enroll")),
+                    )
+                    .route(
+                        "/reset",
+                        post(MfaHandler::reset).layer(require_any_perm!("mfa:reset")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
@@ -1,12 +1,22 @@
 use crate::core::error::AppError;
-use crate::global_error_handler;
+use crate::{global_error_handler, oper_log};
+use crate::middleware::tenant;
+use crate::router::captcha_router::CaptchaRouter;
+use crate::router::dept_router::DeptRouter;
+use crate::router::file_router::FileRouter;
+use crate::router::login_router::LoginRouter;
 use crate::router::menu_router::MenuRouter;
+use crate::router::mfa_router::MfaRouter;
+use crate::router::oper_router::OperRouter;
 use crate::router::role_router::RoleRouter;
+use crate::router::session_router::SessionRouter;
+use crate::router::tenant_router::TenantRouter;
 use crate::router::token_router::TokenRouter;
 use crate::router::user_router::UserRouter;
 use crate::state::AppState;
 use axum::extract::DefaultBodyLimit;
 use axum::http::Method;
+use axum::middleware::from_fn;
 use axum::response::IntoResponse;
 use axum::{Extension, Router};
 use std::sync::Arc;
@@ -15,8 +25,16 @@ use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, Expos
 use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 
+mod captcha_router;
+mod dept_router;
+mod file_router;
+mod login_router;
 mod menu_router;
+mod mfa_router;
+mod oper_router;
 mod role_router;
+mod session_router;
+mod tenant_router;
 mod token_router;
 mod user_router;
 
@@ -31,18 +49,39 @@ pub struct AppRouter;
 impl AppRouter {
     /// 初始化全局路由及中间件配置
     pub fn init(app_state: Arc<AppState>) -> Router {
+        let body_limit = app_state.env.body_limit;
         Router::new()
             .nest(
                 "/api",
                 Router::new()
                     // token路由
                     .merge(TokenRouter::init())
+                    // 验证码路由
+                    .merge(CaptchaRouter::init())
                     // user路由
                     .merge(UserRouter::init())
                     // role路由
                     .merge(RoleRouter::init())
                     // menu路由
                     .merge(MenuRouter::init())
+                    // 部门路由
+                    .merge(DeptRouter::init())
+                    // 登录会话路由
+                    .merge(SessionRouter::init())
+                    // 文件路由
+                    .merge(FileRouter::init())
+                    // 两步验证路由
+                    .merge(MfaRouter::init())
+                    // 登录日志路由
+                    .merge(LoginRouter::init())
+                    // 操作日志路由
+                    .merge(OperRouter::init())
+                    // 租户路由
+                    .merge(TenantRouter::init())
+                    // 操作日志，需在租户识别之内以获取请求所属租户
+                    .layer(oper_log!(&app_state))
+                    // 租户识别，需在全局共享状态之内
+                    .layer(from_fn(tenant))
                     // 全局共享状态
                     .layer(Extension(app_state))
                     // 全局异常处理
@@ -52,8 +91,8 @@ impl AppRouter {
             .fallback(not_found)
             // 开启压缩
             .layer(CompressionLayer::new())
-            // 单次请求最大10M
-            .layer(RequestBodyLimitLayer::new(10 * 1024 * 1024))
+            // 单次请求大小限制，默认10M
+            .layer(RequestBodyLimitLayer::new(body_limit))
             // 禁用请求大小默认限制
             .layer(DefaultBodyLimit::disable())
             // 跨域设置
@@ -72,8 +111,8 @@ impl CorsCfg {
         CorsLayer::new()
             // 允许任意网站访问
             .allow_origin(AllowOrigin::any())
-            // 仅允许GET、POST请求方法
-            .allow_methods(AllowMethods::from(vec![Method::GET, Method::POST]))
+            // 仅允许GET、POST请求方法，PUT仅用于上传分片
+            .allow_methods(AllowMethods::from(vec![Method::GET, Method::POST, Method::PUT]))
             // 允许任意请求头
             .allow_headers(AllowHeaders::any())
             // 暴露任意请求头给客户端
@@ -0,0 +1,26 @@
+use crate::handler::oper_log_handler::OperLogHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::get;
+use axum::Router;
+
+pub struct OperRouter;
+
+/// 操作日志路由
+impl OperRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/oper",
+                Router::new()
+                    .route(
+                        "/log",
+                        get(OperLogHandler::list).layer(require_any_perm!("oper:log")),
+                    )
+                    .route(
+                        "/export",
+                        get(OperLogHandler::export).layer(require_any_perm!("oper:export")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
@@ -29,9 +29,25 @@ impl RoleRouter {
                         "/info",
                         get(RoleHandler::info).layer(require_any_perm!("role:info")),
                     )
+                    .route(
+                        "/list",
+                        get(RoleHandler::list).layer(require_any_perm!("role:list")),
+                    )
                     .route(
                         "/authperm",
                         post(RoleHandler::auth_perm).layer(require_any_perm!("role:authperm")),
+                    )
+                    .route(
+                        "/datascope",
+                        post(RoleHandler::data_scope).layer(require_any_perm!("role:datascope")),
+                    )
+                    .route(
+                        "/parent",
+                        post(RoleHandler::parent).layer(require_any_perm!("role:parent")),
+                    )
+                    .route(
+                        "/effective",
+                        get(RoleHandler::effective).layer(require_any_perm!("role:effective")),
                     ),
             )
             .layer(require_token!())
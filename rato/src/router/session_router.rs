@@ -0,0 +1,31 @@
+use crate::handler::session_handler::SessionHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::{get, post};
+use axum::Router;
+
+pub struct SessionRouter;
+
+/// 登录会话路由
+impl SessionRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/session",
+                Router::new()
+                    .route(
+                        "/list",
+                        get(SessionHandler::list).layer(require_any_perm!("session:list")),
+                    )
+                    .route(
+                        "/revoke",
+                        post(SessionHandler::revoke).layer(require_any_perm!("session:revoke")),
+                    )
+                    .route(
+                        "/revoke_others",
+                        post(SessionHandler::revoke_others)
+                            .layer(require_any_perm!("session:revoke")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
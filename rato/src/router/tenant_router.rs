@@ -0,0 +1,34 @@
+use crate::handler::tenant_handler::TenantHandler;
+use crate::{require_any_perm, require_token};
+use axum::routing::{get, post};
+use axum::Router;
+
+pub struct TenantRouter;
+
+/// 租户路由
+impl TenantRouter {
+    pub fn init() -> Router {
+        Router::new()
+            .nest(
+                "/tenant",
+                Router::new()
+                    .route(
+                        "/add",
+                        post(TenantHandler::add).layer(require_any_perm!("tenant:add")),
+                    )
+                    .route(
+                        "/edit",
+                        post(TenantHandler::edit).layer(require_any_perm!("tenant:edit")),
+                    )
+                    .route(
+                        "/info",
+                        get(TenantHandler::info).layer(require_any_perm!("tenant:info")),
+                    )
+                    .route(
+                        "/list",
+                        get(TenantHandler::list).layer(require_any_perm!("tenant:list")),
+                    ),
+            )
+            .layer(require_token!())
+    }
+}
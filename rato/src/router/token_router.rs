@@ -13,6 +13,9 @@ impl TokenRouter {
             Router::new()
                 .route("/register", post(TokenHandler::register))
                 .route("/login", post(TokenHandler::login))
+                .route("/mfa", post(TokenHandler::mfa))
+                .route("/refresh", post(TokenHandler::refresh))
+                .route("/jwks.json", get(TokenHandler::jwks))
                 .route(
                     "/logout",
                     post(TokenHandler::logout)
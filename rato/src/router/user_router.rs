@@ -17,13 +17,41 @@ impl UserRouter {
                         "/me",
                         get(UserHandler::me).layer(require_any_perm!("user:me")),
                     )
+                    .route(
+                        "/routes",
+                        get(UserHandler::routes).layer(require_any_perm!("user:routes")),
+                    )
                     .route(
                         "/info",
                         get(UserHandler::info).layer(require_any_perm!("user:info")),
                     )
+                    .route(
+                        "/list",
+                        get(UserHandler::list).layer(require_any_perm!("user:list")),
+                    )
                     .route(
                         "/authrole",
                         post(UserHandler::auth_role).layer(require_any_perm!("user:authrole")),
+                    )
+                    .route(
+                        "/password",
+                        post(UserHandler::password).layer(require_any_perm!("user:password")),
+                    )
+                    .route(
+                        "/profile",
+                        post(UserHandler::profile).layer(require_any_perm!("user:profile")),
+                    )
+                    .route(
+                        "/avatar",
+                        post(UserHandler::avatar).layer(require_any_perm!("user:avatar")),
+                    )
+                    .route(
+                        "/status",
+                        post(UserHandler::status).layer(require_any_perm!("user:status")),
+                    )
+                    .route(
+                        "/status_log",
+                        get(UserHandler::status_log).layer(require_any_perm!("user:status_log")),
                     ),
             )
             .layer(require_token!())
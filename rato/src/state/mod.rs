@@ -2,31 +2,94 @@ use std::fmt::Display;
 use async_trait::async_trait;
 use bb8::Pool;
 use bb8_redis::RedisConnectionManager;
-use redis::{AsyncCommands, FromRedisValue, ToRedisArgs};
-use sea_orm::DatabaseConnection;
+use redis::{AsyncCommands, ExistenceCheck, FromRedisValue, Script, SetExpiry, SetOptions, ToRedisArgs};
+use serde::de::DeserializeOwned;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DeleteMany, PrimaryKeyTrait, QueryFilter, Select,
+    UpdateMany,
+};
 use serde::{Deserialize, Serialize};
 use rato_core::database::DbPool;
 use rato_core::redis::RedisPool;
-use crate::config::{DbConfig, GlobalConfig, RedisConfig};
+use rato_core::storage::{FileStorage, LocalStorage};
+use crate::config::{DbConfig, GlobalConfig, JwtConfig, RedisConfig};
 use crate::core::error::AppError;
 use crate::entity::user::LoginUser;
+use crate::utils::perm::CachedMatcher;
+use crate::utils::proxy::TrustedProxies;
+use crate::utils::scope::DataScope;
+use crate::utils::tenant::{TenantEntity, TenantUtils};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 
 /// 全局共享变量
 pub struct AppState {
     pub env: GlobalConfig,
     pub db: DbConfig,
-    pub redis: RedisConfig
+    pub redis: RedisConfig,
+    pub jwt: JwtConfig,
+    // 预编译的用户权限匹配器，按权限版本号及有效期失效，数量有上限
+    pub perm_matchers: RwLock<HashMap<i64, CachedMatcher>>,
+    // 文件存储
+    pub storage: Arc<dyn FileStorage>,
+    // 信任的反向代理
+    pub trusted_proxies: TrustedProxies,
 }
 
 impl AppState {
     /// 初始化
-    pub fn new(env: GlobalConfig, db: DbConfig, redis: RedisConfig) -> Self {
+    pub fn new(env: GlobalConfig, db: DbConfig, redis: RedisConfig, jwt: JwtConfig) -> Self {
+        let storage = Arc::new(LocalStorage::new(Path::new(&env.upload_dir).join("files")));
+        let trusted_proxies = TrustedProxies::parse(env.trusted_proxies.as_deref().unwrap_or_default())
+            .expect("信任的代理配置错误");
         AppState {
             env,
             db,
-            redis
+            redis,
+            jwt,
+            perm_matchers: RwLock::new(HashMap::new()),
+            storage,
+            trusted_proxies,
         }
     }
+
+    /// 读取并反序列化缓存
+    pub async fn cached<T: DeserializeOwned>(&self, key: &str) -> Result<T, AppError> {
+        let value = self.get::<_, String>(key).await?;
+        serde_json::from_str(&value).map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("解析缓存失败")
+        })
+    }
+
+    /// 查询当前租户的数据。租户数据均应通过此类方法查询，以保证租户隔离
+    pub fn find<E: TenantEntity>(&self) -> Select<E> {
+        E::find().filter(E::tenant_column().eq(TenantUtils::current()))
+    }
+
+    /// 按主键查询当前租户的数据
+    pub fn find_by_id<E: TenantEntity>(
+        &self,
+        id: <E::PrimaryKey as PrimaryKeyTrait>::ValueType,
+    ) -> Select<E> {
+        E::find_by_id(id).filter(E::tenant_column().eq(TenantUtils::current()))
+    }
+
+    /// 批量更新当前租户的数据
+    pub fn update_many<E: TenantEntity>(&self) -> UpdateMany<E> {
+        E::update_many().filter(E::tenant_column().eq(TenantUtils::current()))
+    }
+
+    /// 批量删除当前租户的数据
+    pub fn delete_many<E: TenantEntity>(&self) -> DeleteMany<E> {
+        E::delete_many().filter(E::tenant_column().eq(TenantUtils::current()))
+    }
+
+    /// 查询全部租户的数据，仅用于本身不区分租户的场景，如按内容去重的文件存储
+    pub fn find_all_tenants<E: TenantEntity>(&self) -> Select<E> {
+        E::find()
+    }
 }
 
 /// 实现自定义快捷操作数据库trait
@@ -75,6 +138,36 @@ impl RedisPool for AppState {
         Ok(())
     }
 
+    async fn set_nx_ex<K, V>(&self, k: K, v: V, expire: u64) -> Result<bool, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        V: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(expire));
+        let result = connection.set_options::<K, V, Option<String>>(k, v, options).await?;
+        Ok(result.is_some())
+    }
+
+    async fn set_keep_ttl<K, V>(&self, k: K, v: V) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        V: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        let options = SetOptions::default().with_expiration(SetExpiry::KEEPTTL);
+        connection.set_options::<K, V, ()>(k, v, options).await?;
+        Ok(())
+    }
+
     async fn get<K, V>(&self, k: K) -> Result<V, Self::E>
     where
         K: ToRedisArgs + Sync + Send,
@@ -87,6 +180,18 @@ impl RedisPool for AppState {
         Ok(connection.get::<K, V>(k).await?)
     }
 
+    async fn get_del<K, V>(&self, k: K) -> Result<Option<V>, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        V: Sync + Send + FromRedisValue,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        Ok(connection.get_del::<K, Option<V>>(k).await?)
+    }
+
     async fn del<K>(&self, k: K) -> Result<(), Self::E>
     where
         K: ToRedisArgs + Sync + Send,
@@ -119,11 +224,156 @@ impl RedisPool for AppState {
         Ok(())
     }
 
+    async fn expire<K>(&self, k: K, expire: i64) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        connection.expire::<K, ()>(k, expire).await?;
+        Ok(())
+    }
+
+    async fn incr<K>(&self, k: K) -> Result<i64, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        Ok(connection.incr::<K, i64, i64>(k, 1).await?)
+    }
+
+    async fn sadd<K, M>(&self, k: K, m: M) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        connection.sadd::<K, M, ()>(k, m).await?;
+        Ok(())
+    }
+
+    async fn srem<K, M>(&self, k: K, m: M) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        connection.srem::<K, M, ()>(k, m).await?;
+        Ok(())
+    }
+
+    async fn smembers<K, M>(&self, k: K) -> Result<Vec<M>, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: Sync + Send + FromRedisValue,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        Ok(connection.smembers::<K, Vec<M>>(k).await?)
+    }
+
+    async fn zadd<K, M>(&self, k: K, m: M, score: i64) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        connection.zadd::<K, i64, M, ()>(k, m, score).await?;
+        Ok(())
+    }
+
+    async fn zrem<K, M>(&self, k: K, m: M) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        M: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        connection.zrem::<K, M, ()>(k, m).await?;
+        Ok(())
+    }
+
+    async fn zrembyscore<K>(&self, k: K, min: i64, max: i64) -> Result<(), Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        connection.zrembyscore::<K, i64, i64, ()>(k, min, max).await?;
+        Ok(())
+    }
+
+    async fn zcard<K>(&self, k: K) -> Result<i64, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        Ok(connection.zcard::<K, i64>(k).await?)
+    }
+
+    async fn ttl<K>(&self, k: K) -> Result<i64, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        Ok(connection.ttl::<K, i64>(k).await?)
+    }
+
+    async fn eval<K, A, V>(&self, script: &Script, keys: &[K], args: &[A]) -> Result<V, Self::E>
+    where
+        K: ToRedisArgs + Sync + Send,
+        A: ToRedisArgs + Sync + Send,
+        V: Send + FromRedisValue,
+    {
+        let mut connection = self.get_redis_pool().get().await.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("连接Redis异常")
+        })?;
+        let mut invocation = script.prepare_invoke();
+        for key in keys {
+            invocation.key(key);
+        }
+        for arg in args {
+            invocation.arg(arg);
+        }
+        Ok(invocation.invoke_async(&mut *connection).await?)
+    }
+
 }
 
 /// 认证成功后的请求变量，保存在单次请求中。不需要认证的handler无法获取
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RequestState {
     pub login_user: LoginUser,
+    // 会话id，即访问令牌的jti，同时也是刷新令牌族id
+    pub jti: String,
+    // 数据范围，授权通过后写入
+    pub data_scope: DataScope,
 }
 
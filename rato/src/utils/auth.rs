@@ -1,9 +1,121 @@
 use crate::core::error::AppError;
-use std::collections::HashSet;
-use crate::entity::role::RoleType;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use crate::entity::menu::BASIC_PERMISSIONS;
 use crate::entity::user::LoginUser;
 use crate::middleware::{AuthState, CheckType};
 
+/// 权限匹配器。权限值按`:`分段构建为前缀树：
+/// `*`匹配任意单个分段；较短的权限包含其在租户菜单中声明的下级权限，如`user`包含`user:me`；
+/// `!`开头的为显式拒绝，如`!role:remove`，拒绝优先于授予
+#[derive(Debug, Clone)]
+pub struct PermMatcher {
+    allow: PermNode,
+    deny: PermNode,
+    // 声明的全部权限值
+    declared: HashSet<String>,
+    // 是否授予基础权限
+    basic: bool,
+}
+
+impl Default for PermMatcher {
+    fn default() -> Self {
+        PermMatcher {
+            allow: PermNode::default(),
+            deny: PermNode::default(),
+            declared: HashSet::new(),
+            basic: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct PermNode {
+    children: HashMap<String, PermNode>,
+    wildcard: Option<Box<PermNode>>,
+    // 是否为某个权限的最后一个分段
+    end: bool,
+}
+
+impl PermNode {
+    fn insert(&mut self, perm: &str) {
+        let mut node = self;
+        for segment in perm.split(':') {
+            node = if segment == "*" {
+                node.wildcard.get_or_insert_with(Default::default)
+            } else {
+                node.children.entry(segment.to_string()).or_default()
+            };
+        }
+        node.end = true;
+    }
+
+    /// prefix为false时仅完整匹配，不匹配较短权限的下级权限
+    fn matches(&self, segments: &[&str], prefix: bool) -> bool {
+        if self.end && (prefix || segments.is_empty()) {
+            return true;
+        }
+        let Some((first, rest)) = segments.split_first() else {
+            return false;
+        };
+        self.children.get(*first).is_some_and(|node| node.matches(rest, prefix))
+            || self.wildcard.as_ref().is_some_and(|node| node.matches(rest, prefix))
+    }
+}
+
+impl PermMatcher {
+    /// 根据授予的权限列表及声明的全部权限值构建匹配器，默认授予基础权限
+    pub fn new<I, S, D, T>(perms: I, declared: D) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        D: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut matcher = PermMatcher {
+            declared: declared.into_iter().map(|perm| perm.as_ref().trim().to_string()).collect(),
+            ..Default::default()
+        };
+        for perm in perms {
+            let perm = perm.as_ref().trim();
+            match perm.strip_prefix('!') {
+                Some(deny) if !deny.is_empty() => matcher.deny.insert(deny),
+                None if !perm.is_empty() => matcher.allow.insert(perm),
+                _ => {}
+            }
+        }
+        matcher
+    }
+
+    /// 是否授予[`BASIC_PERMISSIONS`]中的基础权限
+    pub fn basic(mut self, basic: bool) -> Self {
+        self.basic = basic;
+        self
+    }
+
+    /// 是否拥有指定权限，包含启用时登录用户均拥有的基础权限。
+    /// 未声明的权限只能通过完整匹配授予，拒绝始终包含下级权限
+    pub fn check(&self, perm: &str) -> bool {
+        let segments = perm.split(':').collect::<Vec<_>>();
+        !self.deny.matches(&segments, true)
+            && ((self.basic && BASIC_PERMISSIONS.contains(&perm))
+                || self.allow.matches(&segments, self.declared.contains(perm)))
+    }
+}
+
+impl LoginUser {
+    /// 权限匹配器。优先使用授权时预编译的匹配器，否则根据权限列表即时构建，此时没有声明的权限值，仅完整匹配
+    pub fn perm_matcher(&self) -> Option<Cow<'_, PermMatcher>> {
+        match &self.matcher {
+            Some(matcher) => Some(Cow::Borrowed(matcher.as_ref())),
+            None => self
+                .get_all_perms()
+                .ok()
+                .map(|perms| Cow::Owned(PermMatcher::new(perms, Vec::<String>::new()))),
+        }
+    }
+}
+
 /// 角色权限检验工具
 pub trait AuthUtils<Cxt, Err, Role, Perm> {
     fn get_all_roles(&self) -> Result<HashSet<Role>, Err>;
@@ -21,8 +133,8 @@ pub trait AuthUtils<Cxt, Err, Role, Perm> {
     fn auth(&self, context: &Cxt) -> bool;
 }
 
-impl AuthUtils<AuthState, AppError, RoleType, String> for LoginUser {
-    fn get_all_roles(&self) -> Result<HashSet<RoleType>, AppError> {
+impl AuthUtils<AuthState, AppError, String, String> for LoginUser {
+    fn get_all_roles(&self) -> Result<HashSet<String>, AppError> {
         if self.roles.is_none() {
             return Err(AppError::Other("无任何角色"));
         }
@@ -30,27 +142,24 @@ impl AuthUtils<AuthState, AppError, RoleType, String> for LoginUser {
             .roles
             .clone()
             .unwrap()
-            .iter()
-            .map(|x| RoleType::from_str(&x))
-            .filter(|r| r.is_some())
-            .map(|r| r.unwrap())
+            .into_iter()
             .collect::<HashSet<_>>())
     }
 
-    fn has_all_roles(&self, roles: Vec<RoleType>) -> bool {
+    fn has_all_roles(&self, roles: Vec<String>) -> bool {
         let role_set = match self.get_all_roles() {
             Ok(r) => r,
             Err(_) => return false,
         };
-        roles.iter().all(|x| role_set.contains(&x))
+        roles.iter().all(|x| role_set.contains(x))
     }
 
-    fn has_any_role(&self, roles: Vec<RoleType>) -> bool {
+    fn has_any_role(&self, roles: Vec<String>) -> bool {
         let role_set = match self.get_all_roles() {
             Ok(r) => r,
             Err(_) => return false,
         };
-        roles.iter().any(|x| role_set.contains(&x))
+        roles.iter().any(|x| role_set.contains(x))
     }
 
     fn get_all_perms(&self) -> Result<HashSet<String>, AppError> {
@@ -61,30 +170,22 @@ impl AuthUtils<AuthState, AppError, RoleType, String> for LoginUser {
         Ok(option
             .unwrap()
             .iter()
-            .map(|x| x.clone())
+            .cloned()
             .collect::<HashSet<_>>())
     }
 
     fn has_all_perms(&self, perms: Vec<String>) -> bool {
-        let perm_set = match self.get_all_perms() {
-            Ok(p) => p,
-            Err(_) => return false,
-        };
-        perms.iter().all(|x| perm_set.contains(x))
+        // 无任何权限时仍拥有基础权限
+        let matcher = self.perm_matcher().unwrap_or_default();
+        perms.iter().all(|x| matcher.check(x))
     }
 
     fn has_any_perm(&self, perms: Vec<String>) -> bool {
-        let perm_set = match self.get_all_perms() {
-            Ok(p) => p,
-            Err(_) => return false,
-        };
-        perms.iter().any(|x| perm_set.contains(x))
+        let matcher = self.perm_matcher().unwrap_or_default();
+        perms.iter().any(|x| matcher.check(x))
     }
 
     fn auth(&self, auth_state: &AuthState) -> bool {
-        if self.uid == 1 {
-            return true;
-        }
         if auth_state.role.is_some() && auth_state.perm.is_some() {
             let p = auth_state.perm.clone().unwrap();
             let pb = match p.check_type {
@@ -121,10 +222,15 @@ impl AuthUtils<AuthState, AppError, RoleType, String> for LoginUser {
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::utils::auth::AuthUtils;
-    use crate::entity::role::{RoleType};
+    use crate::entity::menu::BASIC_PERMISSIONS;
+    use crate::utils::auth::{AuthUtils, PermMatcher};
+    use crate::entity::role::{RequireRole, RoleType};
+    use crate::middleware::AuthState;
+    use crate::{require_all_role, require_any_role};
     use crate::entity::user::LoginUserBuilder;
+    use std::collections::HashSet;
 
     #[test]
     fn check_perms() {
@@ -151,6 +257,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn check_basic_perms() {
+        let user = LoginUserBuilder::default()
+            .uid(1)
+            .name("name".to_string())
+            .perms(Some(vec!["!token:logout".to_string()]))
+            .build()
+            .unwrap();
+        assert!(user.has_any_perm(vec!["token:check".to_string()]));
+        assert!(!user.has_any_perm(vec!["token:logout".to_string()]));
+        assert!(!user.has_any_perm(vec!["role:add".to_string()]));
+        let user = LoginUserBuilder::default()
+            .uid(1)
+            .name("name".to_string())
+            .build()
+            .unwrap();
+        assert!(user.has_all_perms(vec!["user:me".to_string(), "token:logout".to_string()]));
+        assert!(!user.has_any_perm(vec!["user:list".to_string()]));
+    }
+
+    #[test]
+    fn basic_perms() {
+        // 基础权限需为完整的两段权限值且不重复
+        let basic = HashSet::from(BASIC_PERMISSIONS);
+        assert_eq!(basic.len(), BASIC_PERMISSIONS.len());
+        assert!(BASIC_PERMISSIONS.iter().all(|perm| {
+            let segments = perm.split(':').collect::<Vec<_>>();
+            segments.len() == 2 && segments.iter().all(|segment| !segment.is_empty() && *segment != "*")
+        }));
+        let matcher = PermMatcher::default();
+        assert!(BASIC_PERMISSIONS.iter().all(|perm| matcher.check(perm)));
+        let matcher = PermMatcher::default().basic(false);
+        assert!(BASIC_PERMISSIONS.iter().all(|perm| !matcher.check(perm)));
+        // 关闭后仍可通过菜单授予
+        let matcher = PermMatcher::new(["token"], ["token:check", "token:logout"]).basic(false);
+        assert!(matcher.check("token:logout"));
+        assert!(!matcher.check("user:me"));
+    }
+
+    #[test]
+    fn match_perms() {
+        let declared = ["user:me", "user:me1", "menu:info", "menu:edit", "menu:tree", "role:add", "role:remove"];
+        let matcher = PermMatcher::new(["user:*", "*:info", "role", "!role:remove", "menu:tree"], declared);
+        assert!(matcher.check("user:me"));
+        assert!(matcher.check("user:me1"));
+        // 较短的权限只包含已声明的下级权限
+        assert!(!matcher.check("user:me:detail"));
+        assert!(!PermMatcher::new(["user"], declared).check("user:me2"));
+        assert!(PermMatcher::new(["user"], declared).check("user:me1"));
+        // 未声明的权限不包含在较短的权限中
+        assert!(!PermMatcher::new(["role"], Vec::<String>::new()).check("role:add"));
+        assert!(!matcher.check("user"));
+        assert!(matcher.check("menu:info"));
+        assert!(!matcher.check("menu:edit"));
+        assert!(matcher.check("menu:tree"));
+        assert!(!matcher.check("menu:tree1"));
+        assert!(matcher.check("role:add"));
+        // 显式拒绝优先于授予
+        assert!(!matcher.check("role:remove"));
+        assert!(!matcher.check("role:remove:all"));
+        assert!(!PermMatcher::new(["", "!"], declared).check("role:add"));
+        assert!(PermMatcher::new(["*"], declared).check("user:me"));
+    }
+
+    #[test]
+    fn match_many_perms() {
+        let perms = (0..5000).map(|i| format!("module{}:action{}", i % 100, i)).collect::<Vec<_>>();
+        let matcher = PermMatcher::new(&perms, &perms);
+        assert!(matcher.check("module7:action4907"));
+        assert!(!matcher.check("module7:action4908"));
+    }
+
     #[test]
     fn check_roles() {
         let user = LoginUserBuilder::default()
@@ -163,13 +341,32 @@ mod tests {
             .perms(Some(vec!["user".to_string(), "admin".to_string()]))
             .build()
             .unwrap();
-        assert_eq!(user.has_any_role(vec![RoleType::User]), true);
-        assert_eq!(user.has_any_role(vec![RoleType::Api]), false);
-        assert_eq!(user.has_all_roles(vec![RoleType::Admin]), true);
+        assert_eq!(user.has_any_role(vec![RoleType::User.to_string()]), true);
+        assert_eq!(user.has_any_role(vec![RoleType::Api.to_string()]), false);
+        assert_eq!(user.has_all_roles(vec![RoleType::Admin.to_string()]), true);
         assert_eq!(
-            user.has_all_roles(vec![RoleType::Admin, RoleType::Api]),
+            user.has_all_roles(vec![RoleType::Admin.to_string(), RoleType::Api.to_string()]),
             false
         );
     }
 
+    #[test]
+    fn check_custom_roles() {
+        let user = LoginUserBuilder::default()
+            .uid(2)
+            .name("name".to_string())
+            .roles(Some(vec!["auditor".to_string(), "ops".to_string()]))
+            .build()
+            .unwrap();
+        let state = AuthState::role(RequireRole::any(vec!["auditor", "finance"]));
+        assert!(user.auth(&state));
+        let state = AuthState::role(RequireRole::all(vec!["auditor", "finance"]));
+        assert!(!user.auth(&state));
+        let state = AuthState::role(RequireRole::any(vec![RoleType::Admin]));
+        assert!(!user.auth(&state));
+        // 宏支持任意角色值及角色类型
+        let _ = require_any_role!("auditor", RoleType::Admin);
+        let _ = require_all_role!("auditor");
+    }
+
 }
@@ -0,0 +1,307 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::entity::captcha::CaptchaVo;
+use crate::state::AppState;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::Rng;
+use rato_core::redis::RedisPool;
+use std::f32::consts::PI;
+
+// 字母数字验证码字符集，去除易混淆的0、1、I、O
+const CHARSET: &[u8] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const WIDTH: usize = 120;
+const HEIGHT: usize = 40;
+// 字模为5x7点阵，绘制时横向放大3倍、纵向放大4倍
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+const SCALE_X: usize = 3;
+const SCALE_Y: usize = 4;
+
+/// 生成的验证码
+pub struct Captcha {
+    // 校验用的答案
+    pub answer: String,
+    // png图片
+    pub image: Vec<u8>,
+}
+
+/// 图形验证码工具类
+pub struct CaptchaUtils;
+
+impl CaptchaUtils {
+    /// 生成验证码并保存答案，返回验证码id及图片
+    pub async fn create(app_state: &AppState) -> Result<CaptchaVo, AppError> {
+        let env = &app_state.env;
+        let captcha = Self::generate(&env.captcha_type, env.captcha_length)?;
+        let captcha_id = uuid::Uuid::new_v4().simple().to_string();
+        app_state
+            .set_ex(RedisKey::captcha(&captcha_id), captcha.answer, env.captcha_expire)
+            .await?;
+        Ok(CaptchaVo {
+            captcha_id,
+            image: format!("data:image/png;base64,{}", STANDARD.encode(captcha.image)),
+            expires_in: env.captcha_expire,
+        })
+    }
+
+    /// 校验验证码，不区分大小写。无论校验结果如何，验证码均只能使用一次
+    pub async fn verify(
+        app_state: &AppState,
+        captcha_id: Option<&str>,
+        captcha: Option<&str>,
+    ) -> Result<(), AppError> {
+        let (Some(captcha_id), Some(captcha)) = (
+            captcha_id.filter(|captcha_id| !captcha_id.is_empty()),
+            captcha.filter(|captcha| !captcha.is_empty()),
+        ) else {
+            return Err(AppError::Other("请输入验证码"));
+        };
+        let answer = app_state
+            .get_del::<_, String>(RedisKey::captcha(captcha_id))
+            .await?
+            .ok_or(AppError::Other("验证码已失效，请重新获取"))?;
+        if !answer.eq_ignore_ascii_case(captcha.trim()) {
+            return Err(AppError::Other("验证码错误"));
+        }
+        Ok(())
+    }
+
+    /// 按类型生成验证码，类型为arithmetic时生成算术题，否则生成length位字母数字
+    pub fn generate(kind: &str, length: usize) -> Result<Captcha, AppError> {
+        let mut rng = rand::rng();
+        let (text, answer) = match kind {
+            "arithmetic" => Self::arithmetic(&mut rng),
+            _ => Self::alphanumeric(&mut rng, length.clamp(1, 6)),
+        };
+        let image = Self::render(&text, &mut rng)?;
+        Ok(Captcha { answer, image })
+    }
+
+    fn alphanumeric(rng: &mut impl Rng, length: usize) -> (String, String) {
+        let text = (0..length)
+            .map(|_| CHARSET[rng.random_range(0..CHARSET.len())] as char)
+            .collect::<String>();
+        (text.clone(), text)
+    }
+
+    /// 10以内的加、减、乘法，减法结果不为负
+    fn arithmetic(rng: &mut impl Rng) -> (String, String) {
+        let a = rng.random_range(1..10);
+        let b = rng.random_range(1..10);
+        match rng.random_range(0..3) {
+            0 => (format!("{}+{}=?", a, b), (a + b).to_string()),
+            1 => {
+                let (a, b) = (a.max(b), a.min(b));
+                (format!("{}-{}=?", a, b), (a - b).to_string())
+            }
+            _ => (format!("{}*{}=?", a, b), (a * b).to_string()),
+        }
+    }
+
+    /// 绘制文本并做扭曲及干扰处理，编码为png
+    fn render(text: &str, rng: &mut impl Rng) -> Result<Vec<u8>, AppError> {
+        // 先在画布上逐字绘制，每个字符随机偏移、倾斜及着色
+        let mut canvas: Vec<Option<[u8; 3]>> = vec![None; WIDTH * HEIGHT];
+        let count = text.chars().count().max(1);
+        let cell = (WIDTH - 8) / count;
+        let glyph_width = GLYPH_WIDTH * SCALE_X;
+        let glyph_height = GLYPH_HEIGHT * SCALE_Y;
+        for (index, ch) in text.chars().enumerate() {
+            let Some(glyph) = Self::glyph(ch) else {
+                continue;
+            };
+            let color = [
+                rng.random_range(0..120),
+                rng.random_range(0..120),
+                rng.random_range(0..120),
+            ];
+            let left = 4 + index * cell + rng.random_range(0..=cell.saturating_sub(glyph_width));
+            let top = rng.random_range(2..=HEIGHT - glyph_height - 2);
+            let shear = rng.random_range(-0.3f32..0.3);
+            for y in 0..glyph_height {
+                let offset = (shear * (y as f32 - glyph_height as f32 / 2.0)).round() as isize;
+                for x in 0..glyph_width {
+                    if glyph[y / SCALE_Y] & (1 << (GLYPH_WIDTH - 1 - x / SCALE_X)) == 0 {
+                        continue;
+                    }
+                    let cx = (left + x) as isize + offset;
+                    let cy = top + y;
+                    if (0..WIDTH as isize).contains(&cx) {
+                        canvas[cy * WIDTH + cx as usize] = Some(color);
+                    }
+                }
+            }
+        }
+        // 正弦波扭曲后铺到背景上
+        let background = [
+            rng.random_range(220..=255),
+            rng.random_range(220..=255),
+            rng.random_range(220..=255),
+        ];
+        let (phase_x, phase_y) = (rng.random_range(0.0..2.0 * PI), rng.random_range(0.0..2.0 * PI));
+        let mut pixels = vec![0u8; WIDTH * HEIGHT * 3];
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let sx = x as f32 + 2.0 * (2.0 * PI * y as f32 / 30.0 + phase_x).sin();
+                let sy = y as f32 + 3.0 * (2.0 * PI * x as f32 / 60.0 + phase_y).sin();
+                let (sx, sy) = (sx.round() as isize, sy.round() as isize);
+                let color = if (0..WIDTH as isize).contains(&sx) && (0..HEIGHT as isize).contains(&sy) {
+                    canvas[sy as usize * WIDTH + sx as usize].unwrap_or(background)
+                } else {
+                    background
+                };
+                pixels[(y * WIDTH + x) * 3..(y * WIDTH + x) * 3 + 3].copy_from_slice(&color);
+            }
+        }
+        // 干扰线及噪点
+        for _ in 0..4 {
+            let color = [
+                rng.random_range(60..200),
+                rng.random_range(60..200),
+                rng.random_range(60..200),
+            ];
+            let from = (rng.random_range(0..WIDTH / 4) as isize, rng.random_range(0..HEIGHT) as isize);
+            let to = (rng.random_range(WIDTH * 3 / 4..WIDTH) as isize, rng.random_range(0..HEIGHT) as isize);
+            Self::line(&mut pixels, from, to, color);
+        }
+        for _ in 0..WIDTH * HEIGHT / 20 {
+            let (x, y) = (rng.random_range(0..WIDTH), rng.random_range(0..HEIGHT));
+            let color = [rng.random(), rng.random(), rng.random()];
+            pixels[(y * WIDTH + x) * 3..(y * WIDTH + x) * 3 + 3].copy_from_slice(&color);
+        }
+        Self::encode(&pixels)
+    }
+
+    /// Bresenham画线
+    fn line(pixels: &mut [u8], from: (isize, isize), to: (isize, isize), color: [u8; 3]) {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (step_x, step_y) = ((to.0 - x).signum(), (to.1 - y).signum());
+        let mut error = dx + dy;
+        loop {
+            if (0..WIDTH as isize).contains(&x) && (0..HEIGHT as isize).contains(&y) {
+                let index = (y as usize * WIDTH + x as usize) * 3;
+                pixels[index..index + 3].copy_from_slice(&color);
+            }
+            if (x, y) == to {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// 将rgb像素编码为png
+    fn encode(pixels: &[u8]) -> Result<Vec<u8>, AppError> {
+        let mut image = Vec::new();
+        let mut encoder = png::Encoder::new(&mut image, WIDTH as u32, HEIGHT as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(pixels))
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("生成验证码失败")
+            })?;
+        Ok(image)
+    }
+
+    /// 5x7点阵字模，每行低5位有效，高位在左
+    fn glyph(ch: char) -> Option<[u8; GLYPH_HEIGHT]> {
+        let glyph = match ch {
+            '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+            '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+            '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+            '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+            '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+            '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+            '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+            '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+            '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+            '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+            'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+            'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+            'C' => [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110],
+            'D' => [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100],
+            'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+            'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+            'G' => [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111],
+            'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+            'J' => [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100],
+            'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+            'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+            'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+            'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+            'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+            'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+            'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+            'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+            'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+            'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+            'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+            'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010],
+            'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+            'Y' => [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100],
+            'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+            '+' => [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000],
+            '-' => [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000],
+            '*' => [0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b00000],
+            '=' => [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000],
+            '?' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100],
+            _ => return None,
+        };
+        Some(glyph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::captcha::{CaptchaUtils, CHARSET, HEIGHT, WIDTH};
+
+    #[test]
+    fn glyph_coverage() {
+        for ch in CHARSET.iter().map(|ch| *ch as char).chain("0123456789+-*=?".chars()) {
+            assert!(CaptchaUtils::glyph(ch).is_some(), "缺少字模：{}", ch);
+        }
+    }
+
+    #[test]
+    fn alphanumeric_png() {
+        let captcha = CaptchaUtils::generate("alphanumeric", 5).unwrap();
+        assert_eq!(captcha.answer.len(), 5);
+        assert!(captcha.answer.bytes().all(|ch| CHARSET.contains(&ch)));
+        let decoder = png::Decoder::new(captcha.image.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().width, WIDTH as u32);
+        assert_eq!(reader.info().height, HEIGHT as u32);
+    }
+
+    #[test]
+    fn arithmetic_answer() {
+        for _ in 0..50 {
+            let (text, answer) = CaptchaUtils::arithmetic(&mut rand::rng());
+            let expr = text.trim_end_matches("=?");
+            let (a, op, b) = (
+                expr[0..1].parse::<i32>().unwrap(),
+                &expr[1..2],
+                expr[2..3].parse::<i32>().unwrap(),
+            );
+            let expected = match op {
+                "+" => a + b,
+                "-" => a - b,
+                _ => a * b,
+            };
+            assert!(expected >= 0);
+            assert_eq!(answer, expected.to_string());
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::entity::{file, user};
+use crate::entity::prelude::File;
+use crate::state::AppState;
+use crate::utils::image::{ImageUtils, THUMBNAIL_SIZES};
+use crate::utils::scope::DataScope;
+use crate::utils::tenant::TenantUtils;
+use axum::body::Bytes;
+use axum::extract::Multipart;
+use chrono::Utc;
+use rato_core::redis::RedisPool;
+use redis::Script;
+use sea_orm::sea_query::Query;
+use sea_orm::{ActiveModelTrait, ColumnTrait, Condition, ModelTrait, PaginatorTrait, QueryFilter, Set};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+/// 文件内容锁的有效期，单位秒。持有锁的请求异常退出时到期自动释放
+const LOCK_EXPIRE: u64 = 5 * 60;
+
+/// 等待文件内容锁的重试次数及间隔
+const LOCK_RETRIES: u32 = 100;
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 识别图片格式读取的文件头字节数
+const SNIFF_LEN: u64 = 16;
+
+/// 锁仍由本次请求持有时才释放，避免锁过期后误删其他请求的锁
+static UNLOCK_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#,
+    )
+});
+
+/// Range请求头解析结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    // 返回完整内容
+    Full,
+    // 返回[start, end]闭区间内的内容
+    Partial(u64, u64),
+    // 范围超出文件大小
+    Unsatisfiable,
+}
+
+/// 上传的文件
+pub struct Upload {
+    pub name: String,
+    pub mime: String,
+    pub bytes: Bytes,
+}
+
+/// 文件工具类
+pub struct FileUtils;
+
+impl FileUtils {
+    /// 读取multipart表单的file字段
+    pub async fn upload(multipart: &mut Multipart) -> Result<Upload, AppError> {
+        while let Some(field) = multipart.next_field().await? {
+            if field.name() != Some("file") {
+                continue;
+            }
+            let name = field.file_name().unwrap_or_default().to_string();
+            let mime = field
+                .content_type()
+                .unwrap_or(mime::APPLICATION_OCTET_STREAM.as_ref())
+                .to_string();
+            let bytes = field.bytes().await?;
+            if bytes.is_empty() {
+                return Err(AppError::Other("文件内容不能为空"));
+            }
+            return Ok(Upload { name, mime, bytes });
+        }
+        Err(AppError::Other("请选择上传的文件"))
+    }
+
+    /// 保存文件内容并写入文件记录。内容以sha256为key存储，相同内容只存储一份
+    pub async fn save(app_state: &AppState, owner_id: i64, upload: &Upload) -> Result<file::Model, AppError> {
+        let sha256 = Self::sha256(&upload.bytes);
+        Self::locked(app_state, &sha256, async {
+            app_state.storage.put(&sha256, &upload.bytes).await.map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("保存文件失败")
+            })?;
+            Self::insert(app_state, owner_id, &upload.name, &upload.mime, upload.bytes.len() as i64, sha256.clone()).await
+        })
+        .await
+    }
+
+    /// 保存图片。按文件头识别格式后重新编码，并保存各尺寸的缩略图
+    pub async fn save_image(app_state: &AppState, owner_id: i64, upload: &Upload) -> Result<file::Model, AppError> {
+        let bytes = upload.bytes.clone();
+        let processed = tokio::task::spawn_blocking(move || ImageUtils::process(&bytes))
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("处理图片失败")
+            })??;
+        let sha256 = Self::sha256(&processed.bytes);
+        // 扩展名与重新编码后的格式保持一致
+        let name = Self::file_name(&upload.name);
+        let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+        let name = format!("{}.{}", stem, processed.extension);
+        Self::locked(app_state, &sha256, async {
+            for (size, bytes) in &processed.thumbnails {
+                app_state
+                    .storage
+                    .put(&ImageUtils::thumbnail_key(&sha256, *size), bytes)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("{:?}", e);
+                        AppError::Other("保存缩略图失败")
+                    })?;
+            }
+            app_state.storage.put(&sha256, &processed.bytes).await.map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("保存文件失败")
+            })?;
+            Self::insert(app_state, owner_id, &name, processed.mime, processed.bytes.len() as i64, sha256.clone()).await
+        })
+        .await
+    }
+
+    /// 写入文件记录，内容需已保存到存储中
+    pub async fn insert(
+        app_state: &AppState,
+        owner_id: i64,
+        name: &str,
+        mime: &str,
+        size: i64,
+        sha256: String,
+    ) -> Result<file::Model, AppError> {
+        let file = file::ActiveModel {
+            uid: Set(uuid::Uuid::new_v4().simple().to_string()),
+            tenant_id: Set(TenantUtils::current()),
+            owner_id: Set(owner_id),
+            name: Set(Self::file_name(name)),
+            size: Set(size),
+            mime: Set(mime.to_string()),
+            sha256: Set(sha256),
+            create_time: Set(Utc::now()),
+        }
+        .insert(&app_state.db.connection)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("保存文件记录失败")
+        })?;
+        Ok(file)
+    }
+
+    /// 删除文件记录，内容不再被任何记录引用时连同缩略图一并删除。
+    /// 统计引用及删除内容期间持有内容锁，相同内容的并发上传等待删除完成后再写入
+    pub async fn remove(app_state: &AppState, file: file::Model) -> Result<(), AppError> {
+        let sha256 = file.sha256.clone();
+        let image = file.mime.starts_with("image/");
+        Self::locked(app_state, &sha256, async {
+            file.delete(&app_state.db.connection).await.map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("删除文件失败")
+            })?;
+            // 存储不区分租户，需统计全部租户的引用
+            let references = app_state.find_all_tenants::<File>()
+                .filter(file::Column::Sha256.eq(&sha256))
+                .count(&app_state.db.connection)
+                .await?;
+            if references == 0 {
+                let mut keys = vec![sha256.clone()];
+                if image {
+                    keys.extend(THUMBNAIL_SIZES.iter().map(|size| ImageUtils::thumbnail_key(&sha256, *size)));
+                }
+                for key in keys {
+                    app_state.storage.delete(&key).await.map_err(|e| {
+                        tracing::error!("{:?}", e);
+                        AppError::Other("删除文件内容失败")
+                    })?;
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// 持有文件内容锁执行。写入内容及文件记录、删除记录及无引用的内容均需持有，
+    /// 避免删除时统计引用后、删除内容前有相同内容的上传写入记录
+    pub async fn locked<F, T>(app_state: &AppState, sha256: &str, f: F) -> Result<T, AppError>
+    where
+        F: Future<Output = Result<T, AppError>>,
+    {
+        let key = RedisKey::file_lock(sha256);
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let mut retries = 0;
+        while !app_state.set_nx_ex(&key, &token, LOCK_EXPIRE).await? {
+            retries += 1;
+            if retries >= LOCK_RETRIES {
+                return Err(AppError::Other("文件正在处理，请稍后重试"));
+            }
+            tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+        let result = f.await;
+        if let Err(e) = app_state.eval::<_, _, i64>(&UNLOCK_SCRIPT, &[&key], &[&token]).await {
+            tracing::error!("释放文件锁失败：{:?}", e);
+        }
+        result
+    }
+
+    /// 文件内容按文件头识别为图片，且与记录的类型一致。仅此时允许浏览器内联展示
+    pub async fn is_image(app_state: &AppState, file: &file::Model) -> bool {
+        let mut head = Vec::new();
+        let read = match app_state.storage.read(&file.sha256, 0, SNIFF_LEN).await {
+            Ok(mut reader) => reader.read_to_end(&mut head).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = read {
+            tracing::warn!("读取文件头失败：{:?}", e);
+            return false;
+        }
+        ImageUtils::sniff(&head).is_some_and(|format| format.to_mime_type() == file.mime)
+    }
+
+    pub fn sha256(bytes: &[u8]) -> String {
+        Sha256::digest(bytes)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 解析Range请求头。仅支持单个范围，多个范围或格式错误时返回完整内容
+    pub fn parse_range(header: Option<&str>, size: u64) -> ByteRange {
+        let Some((start, end)) = header
+            .and_then(|header| header.trim().strip_prefix("bytes="))
+            .filter(|spec| !spec.contains(','))
+            .and_then(|spec| spec.split_once('-'))
+        else {
+            return ByteRange::Full;
+        };
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            // 后缀范围，取最后end个字节
+            let Ok(suffix) = end.parse::<u64>() else {
+                return ByteRange::Full;
+            };
+            if suffix == 0 || size == 0 {
+                return ByteRange::Unsatisfiable;
+            }
+            return ByteRange::Partial(size - suffix.min(size), size - 1);
+        }
+        let Ok(start) = start.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        let end = match end {
+            "" => size.saturating_sub(1),
+            end => match end.parse::<u64>() {
+                Ok(end) if end >= start => end.min(size.saturating_sub(1)),
+                _ => return ByteRange::Full,
+            },
+        };
+        if start >= size {
+            return ByteRange::Unsatisfiable;
+        }
+        ByteRange::Partial(start, end)
+    }
+
+    /// 可下载文件的查询条件：数据范围内用户上传的文件，以及任一用户正在使用的头像。
+    /// 头像需在用户列表等处展示给数据范围外的用户，其余文件仅上传者及其数据范围可见
+    pub fn downloadable(scope: &DataScope) -> Condition {
+        if scope.all {
+            return Condition::all();
+        }
+        Condition::any().add(scope.condition::<File>()).add(
+            file::Column::Uid.in_subquery(
+                Query::select()
+                    .column(user::Column::Avatar)
+                    .from(user::Entity)
+                    .and_where(user::Column::Avatar.is_not_null())
+                    .to_owned(),
+            ),
+        )
+    }
+
+    /// 下载时的Content-Disposition，文件名按RFC 5987编码
+    pub fn content_disposition(name: &str, inline: bool) -> String {
+        let disposition = if inline { "inline" } else { "attachment" };
+        format!("{}; filename*=UTF-8''{}", disposition, urlencoding::encode(name))
+    }
+
+    /// 只保留文件名的最后一段，去除客户端路径
+    pub fn file_name(name: &str) -> String {
+        let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+        match name {
+            "" => "file".to_string(),
+            name => name.chars().take(255).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entity::prelude::File;
+    use crate::utils::file::{ByteRange, FileUtils};
+    use crate::utils::scope::DataScope;
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    #[test]
+    fn downloadable() {
+        let sql = |scope: &DataScope| {
+            File::find().filter(FileUtils::downloadable(scope)).build(DbBackend::MySql).to_string()
+        };
+        assert!(!sql(&DataScope::all(7)).contains("`owner_id` ="));
+        // 仅本人上传的文件及头像可下载
+        let own = DataScope {
+            uid: 7,
+            own: true,
+            ..Default::default()
+        };
+        assert!(sql(&own).ends_with(
+            "WHERE `t_file`.`owner_id` = 7 OR `t_file`.`uid` IN (SELECT `avatar` FROM `t_user` WHERE `t_user`.`avatar` IS NOT NULL)"
+        ));
+        // 没有任何数据范围时仍可下载头像
+        let none = DataScope {
+            uid: 7,
+            ..Default::default()
+        };
+        assert!(sql(&none).ends_with(
+            "WHERE 1 = 0 OR `t_file`.`uid` IN (SELECT `avatar` FROM `t_user` WHERE `t_user`.`avatar` IS NOT NULL)"
+        ));
+    }
+
+    #[test]
+    fn parse_range() {
+        assert_eq!(FileUtils::parse_range(None, 100), ByteRange::Full);
+        assert_eq!(FileUtils::parse_range(Some("bytes=0-9"), 100), ByteRange::Partial(0, 9));
+        assert_eq!(FileUtils::parse_range(Some("bytes=90-"), 100), ByteRange::Partial(90, 99));
+        assert_eq!(FileUtils::parse_range(Some("bytes=90-200"), 100), ByteRange::Partial(90, 99));
+        assert_eq!(FileUtils::parse_range(Some("bytes=-10"), 100), ByteRange::Partial(90, 99));
+        assert_eq!(FileUtils::parse_range(Some("bytes=-200"), 100), ByteRange::Partial(0, 99));
+        assert_eq!(FileUtils::parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(FileUtils::parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(FileUtils::parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(FileUtils::parse_range(Some("bytes=9-1"), 100), ByteRange::Full);
+        assert_eq!(FileUtils::parse_range(Some("items=0-9"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn file_name() {
+        assert_eq!(FileUtils::file_name("C:\\Users\\a\\头像.png"), "头像.png");
+        assert_eq!(FileUtils::file_name("../../etc/passwd"), "passwd");
+        assert_eq!(FileUtils::file_name(""), "file");
+        assert_eq!(
+            FileUtils::content_disposition("头像 1.png", true),
+            "inline; filename*=UTF-8''%E5%A4%B4%E5%83%8F%201.png"
+        );
+        assert_eq!(
+            FileUtils::content_disposition("a.html", false),
+            "attachment; filename*=UTF-8''a.html"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use crate::core::error::AppError;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+
+/// 缩略图尺寸，单位像素
+pub const THUMBNAIL_SIZES: [u32; 3] = [64, 128, 256];
+
+/// 重新编码后的最大边长，单位像素
+const MAX_SIDE: u32 = 1024;
+
+/// 解码时允许的最大边长，防止解压炸弹
+const MAX_DIMENSION: u32 = 4096;
+
+/// 解码时单次分配的内存上限，单位字节。4096×4096的RGBA8图片需要64M
+const MAX_ALLOC: u64 = 64 * 1024 * 1024;
+
+/// JPEG重新编码的质量
+const JPEG_QUALITY: u8 = 85;
+
+/// 处理后的图片
+pub struct Processed {
+    pub mime: &'static str,
+    // 文件扩展名
+    pub extension: &'static str,
+    pub bytes: Vec<u8>,
+    // 各尺寸的缩略图，与THUMBNAIL_SIZES一一对应
+    pub thumbnails: Vec<(u32, Vec<u8>)>,
+}
+
+/// 图片工具类
+pub struct ImageUtils;
+
+impl ImageUtils {
+    /// 按文件头识别图片格式，不信任客户端声明的类型
+    pub fn sniff(bytes: &[u8]) -> Option<ImageFormat> {
+        image::guess_format(bytes).ok().filter(|format| {
+            matches!(
+                format,
+                ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP
+            )
+        })
+    }
+
+    /// 解码后重新编码并生成缩略图。重新编码会丢弃EXIF等元数据，方向信息先应用到像素上。
+    /// JPEG仍编码为JPEG，其余格式编码为PNG，动图只保留第一帧
+    pub fn process(bytes: &[u8]) -> Result<Processed, AppError> {
+        let format = Self::sniff(bytes).ok_or(AppError::Other("仅支持PNG、JPEG、GIF、WEBP格式的图片"))?;
+        let mut image = Self::decode(bytes, format).map_err(|e| {
+            tracing::warn!("解码图片失败：{:?}", e);
+            AppError::Other("图片已损坏或尺寸过大")
+        })?;
+        if image.width() > MAX_SIDE || image.height() > MAX_SIDE {
+            image = image.resize(MAX_SIDE, MAX_SIDE, FilterType::Lanczos3);
+        }
+        let output = match format {
+            ImageFormat::Jpeg => ImageFormat::Jpeg,
+            _ => ImageFormat::Png,
+        };
+        let thumbnails = THUMBNAIL_SIZES
+            .iter()
+            .map(|&size| {
+                let thumbnail = image.resize_to_fill(size, size, FilterType::Lanczos3);
+                Ok((size, Self::encode(&thumbnail, output)?))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(Processed {
+            mime: output.to_mime_type(),
+            extension: output.extensions_str()[0],
+            bytes: Self::encode(&image, output)?,
+            thumbnails,
+        })
+    }
+
+    /// 缩略图的存储key
+    pub fn thumbnail_key(sha256: &str, size: u32) -> String {
+        format!("{}_{}", sha256, size)
+    }
+
+    fn decode(bytes: &[u8], format: ImageFormat) -> image::ImageResult<DynamicImage> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_DIMENSION);
+        limits.max_image_height = Some(MAX_DIMENSION);
+        limits.max_alloc = Some(MAX_ALLOC);
+        let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+        reader.limits(limits);
+        let mut decoder = reader.into_decoder()?;
+        let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        Ok(image)
+    }
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, AppError> {
+        let mut bytes = Vec::new();
+        let encoded = match format {
+            // JPEG不支持透明通道
+            ImageFormat::Jpeg => image
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)),
+            format => image.write_to(&mut Cursor::new(&mut bytes), format),
+        };
+        encoded.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("图片编码失败")
+        })?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::image::{ImageUtils, THUMBNAIL_SIZES};
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn sniff() {
+        assert_eq!(ImageUtils::sniff(&png(1, 1)), Some(ImageFormat::Png));
+        assert_eq!(ImageUtils::sniff(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), None);
+        assert_eq!(ImageUtils::sniff(b"GIF89a"), Some(ImageFormat::Gif));
+        assert!(ImageUtils::process(b"\x89PNG\r\n\x1a\nbroken").is_err());
+    }
+
+    #[test]
+    fn limits() {
+        assert!(ImageUtils::process(&png(4097, 1)).is_err());
+    }
+
+    #[test]
+    fn process() {
+        let processed = ImageUtils::process(&png(2000, 500)).unwrap();
+        assert_eq!(processed.mime, "image/png");
+        let image = image::load_from_memory(&processed.bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (1024, 256));
+        for ((size, bytes), expected) in processed.thumbnails.iter().zip(THUMBNAIL_SIZES) {
+            let thumbnail = image::load_from_memory(bytes).unwrap();
+            assert_eq!(*size, expected);
+            assert_eq!((thumbnail.width(), thumbnail.height()), (expected, expected));
+        }
+    }
+}
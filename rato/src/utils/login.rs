@@ -0,0 +1,211 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::core::result::ClientInfo;
+use crate::entity::login_log;
+use crate::state::AppState;
+use crate::utils::tenant::TenantUtils;
+use chrono::Utc;
+use rato_core::redis::RedisPool;
+use redis::Script;
+use sea_orm::{ActiveModelTrait, Set};
+use std::sync::LazyLock;
+
+/// 记录一次失败并在达到阈值时锁定，计数与锁定在同一脚本中原子执行，并发失败不会漏计或重复锁定。
+/// KEYS：失败记录、锁定标记、连续锁定次数；ARGV：当前毫秒时间戳、窗口秒数、成员、阈值、各次锁定的时长。
+/// 返回`{0, 0}`未锁定，`{1, 时长}`本次锁定，`{2, 剩余秒数}`已被其他请求锁定
+static FAIL_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local ttl = redis.call('TTL', KEYS[2])
+if ttl > 0 then
+    return {2, ttl}
+end
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], 0, now - window * 1000)
+redis.call('ZADD', KEYS[1], now, ARGV[3])
+redis.call('EXPIRE', KEYS[1], window)
+if redis.call('ZCARD', KEYS[1]) < tonumber(ARGV[4]) then
+    return {0, 0}
+end
+local level = redis.call('INCR', KEYS[3])
+local lockout = tonumber(ARGV[math.min(level, #ARGV - 4) + 4])
+redis.call('SET', KEYS[2], level, 'EX', lockout)
+redis.call('EXPIRE', KEYS[3], lockout + window)
+redis.call('DEL', KEYS[1])
+return {1, lockout}
+"#,
+    )
+});
+
+/// 登录防暴力破解。按账号及ip统计滑动窗口内的失败次数，超过阈值后锁定，连续锁定时锁定时长按指数增长
+pub struct LoginGuard<'a> {
+    app_state: &'a AppState,
+    account: String,
+    client: &'a ClientInfo,
+}
+
+impl<'a> LoginGuard<'a> {
+    pub fn new(app_state: &'a AppState, account: &str, client: &'a ClientInfo) -> Self {
+        LoginGuard {
+            app_state,
+            account: account.to_string(),
+            client,
+        }
+    }
+
+    /// 账号或ip处于锁定中时返回[`AppError::TooManyAttempts`]
+    pub async fn check(&self) -> Result<(), AppError> {
+        for (subject, _) in self.subjects() {
+            let ttl = self.app_state.ttl(RedisKey::login_lock(&subject)).await?;
+            if ttl > 0 {
+                return Err(AppError::TooManyAttempts(ttl));
+            }
+        }
+        Ok(())
+    }
+
+    /// 记录一次失败。任一维度达到阈值或已被锁定时返回[`AppError::TooManyAttempts`]
+    pub async fn fail(&self) -> Result<(), AppError> {
+        let env = &self.app_state.env;
+        let now = Utc::now().timestamp_millis();
+        let member = format!("{}:{}", now, uuid::Uuid::new_v4().simple());
+        // 解锁后窗口内再次达到阈值时锁定时长继续翻倍，直至上限
+        let lockouts = Self::lockouts(env.login_lockout, env.login_lockout_max);
+        let mut retry_after = 0;
+        for (subject, threshold) in self.subjects() {
+            let keys = [
+                RedisKey::login_failures(&subject),
+                RedisKey::login_lock(&subject),
+                RedisKey::login_lock_level(&subject),
+            ];
+            let mut args = vec![
+                now.to_string(),
+                env.login_failure_window.to_string(),
+                member.clone(),
+                threshold.to_string(),
+            ];
+            args.extend(lockouts.iter().map(|lockout| lockout.to_string()));
+            let (locked, seconds) = self.app_state.eval::<_, _, (i64, i64)>(&FAIL_SCRIPT, &keys, &args).await?;
+            if locked == 1 {
+                tracing::warn!(target: "audit", "登录失败次数过多，锁定{}，时长{}秒", subject, seconds);
+            }
+            retry_after = retry_after.max(seconds);
+        }
+        if retry_after > 0 {
+            return Err(AppError::TooManyAttempts(retry_after));
+        }
+        Ok(())
+    }
+
+    /// 是否需要校验验证码。始终启用，或账号、ip任一维度在窗口内的失败次数达到阈值时需要
+    pub async fn captcha_required(&self) -> Result<bool, AppError> {
+        let env = &self.app_state.env;
+        if env.captcha_enabled {
+            return Ok(true);
+        }
+        if env.captcha_after_failures <= 0 {
+            return Ok(false);
+        }
+        let now = Utc::now().timestamp_millis();
+        for (subject, _) in self.subjects() {
+            let key = RedisKey::login_failures(&subject);
+            self.app_state
+                .zrembyscore(&key, 0, now - env.login_failure_window * 1000)
+                .await?;
+            if self.app_state.zcard(&key).await? >= env.captcha_after_failures {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// 登录成功后清除账号的失败记录及连续锁定次数。ip的记录保留，避免用已知账号重置ip计数
+    pub async fn succeed(&self) {
+        let subject = Self::account_subject(&self.account);
+        let _ = self.app_state.del(RedisKey::login_failures(&subject)).await;
+        let _ = self.app_state.del(RedisKey::login_lock_level(&subject)).await;
+    }
+
+    /// 写入登录日志。写入失败不影响登录结果
+    pub async fn record(&self, user_id: Option<i64>, success: bool, message: &str) {
+        let result = login_log::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
+            user_id: Set(user_id),
+            account: Set(self.account.clone()),
+            ip: Set(self.client.ip.clone()),
+            device: Set(self.client.device.clone()),
+            user_agent: Set(self.client.user_agent.clone()),
+            success: Set(success),
+            message: Set(message.to_string()),
+            create_time: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db.connection)
+        .await;
+        if let Err(e) = result {
+            tracing::error!("写入登录日志失败：{:?}", e);
+        }
+    }
+
+    /// 解除账号或ip的锁定，同时清除失败记录及连续锁定次数
+    pub async fn unlock(app_state: &AppState, subject: &str) -> bool {
+        let mut unlocked = app_state.del(RedisKey::login_lock(subject)).await.is_ok();
+        unlocked |= app_state.del(RedisKey::login_failures(subject)).await.is_ok();
+        unlocked |= app_state.del(RedisKey::login_lock_level(subject)).await.is_ok();
+        unlocked
+    }
+
+    pub fn account_subject(account: &str) -> String {
+        format!("account:{}", account)
+    }
+
+    pub fn ip_subject(ip: &str) -> String {
+        format!("ip:{}", ip)
+    }
+
+    /// 第level次锁定的时长，从base开始翻倍，不超过max
+    pub fn lockout(base: i64, max: i64, level: i64) -> i64 {
+        let shift = (level - 1).clamp(0, 32) as u32;
+        base.saturating_mul(1i64 << shift).min(max)
+    }
+
+    /// 依次为第1次、第2次……锁定的时长，达到上限后结束，之后的锁定均使用最后一个
+    pub fn lockouts(base: i64, max: i64) -> Vec<i64> {
+        let mut lockouts = vec![Self::lockout(base, max, 1)];
+        for level in 2..=33 {
+            let lockout = Self::lockout(base, max, level);
+            if lockout == lockouts[lockouts.len() - 1] {
+                break;
+            }
+            lockouts.push(lockout);
+        }
+        lockouts
+    }
+
+    /// 统计维度及其失败阈值
+    fn subjects(&self) -> [(String, i64); 2] {
+        let env = &self.app_state.env;
+        [
+            (Self::account_subject(&self.account), env.login_account_failures),
+            (Self::ip_subject(&self.client.ip), env.login_ip_failures),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::login::LoginGuard;
+
+    #[test]
+    fn lockout_backoff() {
+        assert_eq!(LoginGuard::lockout(60, 3600, 1), 60);
+        assert_eq!(LoginGuard::lockout(60, 3600, 2), 120);
+        assert_eq!(LoginGuard::lockout(60, 3600, 4), 480);
+        assert_eq!(LoginGuard::lockout(60, 3600, 7), 3600);
+        assert_eq!(LoginGuard::lockout(60, 3600, 100), 3600);
+        assert_eq!(LoginGuard::lockout(60, 3600, 0), 60);
+        assert_eq!(LoginGuard::lockouts(60, 3600), vec![60, 120, 240, 480, 960, 1920, 3600]);
+        assert_eq!(LoginGuard::lockouts(60, 60), vec![60]);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::entity::mfa::{MfaChallenge, MfaPending};
+use crate::entity::mfa_recovery_code;
+use crate::entity::prelude::MfaRecoveryCode;
+use crate::entity::user;
+use crate::state::AppState;
+use crate::utils::jwt::JwtUtils;
+use crate::utils::tenant::TenantUtils;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use qrcode::{Color, QrCode};
+use rand::{Rng, RngCore};
+use rato_core::redis::RedisPool;
+use redis::Script;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::sync::LazyLock;
+use subtle::ConstantTimeEq;
+
+type HmacSha1 = Hmac<Sha1>;
+
+const BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const DIGITS: u32 = 6;
+// 时间步长，单位秒
+const PERIOD: i64 = 30;
+// 允许前后各1个时间步的时钟偏差
+const SKEW: i64 = 1;
+const RECOVERY_CODES: usize = 10;
+// 绑定时生成的密钥需在有效期内确认，单位秒
+pub const ENROLL_EXPIRE: u64 = 10 * 60;
+
+/// 时间步大于已使用的时间步时写入并返回1，否则返回0。比较与写入原子执行，并发提交同一验证码时只有一个成功
+static STEP_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+local last = tonumber(redis.call('GET', KEYS[1]) or '-1')
+if tonumber(ARGV[1]) <= last then
+    return 0
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+return 1
+"#,
+    )
+});
+
+/// 两步验证工具类，动态验证码遵循RFC 6238（TOTP），HMAC-SHA1、6位、30秒
+pub struct MfaUtils;
+
+impl MfaUtils {
+    /// 生成160位随机密钥，base32编码
+    pub fn secret() -> String {
+        let mut bytes = [0u8; 20];
+        rand::rng().fill_bytes(&mut bytes);
+        Self::base32_encode(&bytes)
+    }
+
+    /// 供验证器应用扫码的密钥地址
+    pub fn uri(issuer: &str, account: &str, secret: &str) -> String {
+        let issuer = urlencoding::encode(issuer);
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            issuer,
+            urlencoding::encode(account),
+            secret,
+            issuer,
+            DIGITS,
+            PERIOD
+        )
+    }
+
+    /// RFC 4226 HOTP，counter为TOTP的时间步
+    pub fn hotp(key: &[u8], counter: u64) -> String {
+        let mut mac = HmacSha1::new_from_slice(key).expect("HMAC可接受任意长度的密钥");
+        mac.update(&counter.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        format!("{:0width$}", binary % 10u32.pow(DIGITS), width = DIGITS as usize)
+    }
+
+    /// 校验动态验证码，返回匹配的时间步
+    pub fn verify_totp(secret: &str, code: &str, timestamp: i64) -> Option<i64> {
+        let key = Self::base32_decode(secret)?;
+        if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let step = timestamp / PERIOD;
+        (step - SKEW..=step + SKEW).find(|step| {
+            *step >= 0 && bool::from(Self::hotp(&key, *step as u64).as_bytes().ct_eq(code.as_bytes()))
+        })
+    }
+
+    /// 校验登录时提交的动态验证码或恢复码。动态验证码的同一时间步只能使用一次
+    pub async fn verify(app_state: &AppState, user: &user::Model, code: &str) -> Result<bool, AppError> {
+        let Some(secret) = user.mfa_secret.as_deref().filter(|_| user.mfa_enabled) else {
+            return Ok(false);
+        };
+        let code = code.trim();
+        if code.len() != DIGITS as usize {
+            return Self::consume_recovery_code(app_state, user.uid, code).await;
+        }
+        let Some(step) = Self::verify_totp(secret, code, Utc::now().timestamp()) else {
+            return Ok(false);
+        };
+        Self::consume_step(app_state, user.uid, step).await
+    }
+
+    /// 账号密码校验通过后生成临时令牌，凭临时令牌及动态验证码换取正式令牌
+    pub async fn challenge(app_state: &AppState, user: &user::Model) -> Result<MfaChallenge, AppError> {
+        // 与刷新令牌相同，缓存中只保存哈希
+        let mfa_token = JwtUtils::refresh_token();
+        let expire = app_state.env.mfa_pending_expire;
+        let pending = MfaPending {
+            uid: user.uid,
+            account: user.account.clone(),
+        };
+        app_state
+            .set_ex(
+                RedisKey::mfa_pending(&JwtUtils::hash_refresh_token(&mfa_token)),
+                pending,
+                expire as u64,
+            )
+            .await?;
+        Ok(MfaChallenge {
+            mfa_required: true,
+            mfa_token,
+            expires_in: expire,
+        })
+    }
+
+    /// 生成恢复码，格式为`xxxxx-xxxxx`
+    pub fn recovery_codes() -> Vec<String> {
+        let mut rng = rand::rng();
+        (0..RECOVERY_CODES)
+            .map(|_| {
+                let code = (0..10)
+                    .map(|_| BASE32[rng.random_range(0..BASE32.len())].to_ascii_lowercase() as char)
+                    .collect::<String>();
+                format!("{}-{}", &code[..5], &code[5..])
+            })
+            .collect()
+    }
+
+    /// 恢复码哈希，忽略大小写及分隔符
+    pub fn hash_recovery_code(code: &str) -> String {
+        let normalized = code
+            .chars()
+            .filter(|ch| ch.is_ascii_alphanumeric())
+            .map(|ch| ch.to_ascii_lowercase())
+            .collect::<String>();
+        Sha256::digest(normalized.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// 保存新的恢复码，用户原有的恢复码全部作废
+    pub async fn save_recovery_codes<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+        uid: i64,
+        codes: &[String],
+    ) -> Result<(), AppError> {
+        Self::remove_recovery_codes(app_state, connection, uid).await?;
+        let now = Utc::now();
+        MfaRecoveryCode::insert_many(codes.iter().map(|code| mfa_recovery_code::ActiveModel {
+            tenant_id: Set(TenantUtils::current()),
+            user_id: Set(uid),
+            code_hash: Set(Self::hash_recovery_code(code)),
+            create_time: Set(now),
+            ..Default::default()
+        }))
+        .exec(connection)
+        .await?;
+        Ok(())
+    }
+
+    /// 删除用户的全部恢复码
+    pub async fn remove_recovery_codes<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+        uid: i64,
+    ) -> Result<(), AppError> {
+        app_state
+            .delete_many::<MfaRecoveryCode>()
+            .filter(mfa_recovery_code::Column::UserId.eq(uid))
+            .exec(connection)
+            .await?;
+        Ok(())
+    }
+
+    /// 将内容生成二维码png，返回data url
+    pub fn qr_code(content: &str) -> Result<String, AppError> {
+        // 每个模块6像素，四周保留4个模块的空白
+        const SCALE: usize = 6;
+        const QUIET: usize = 4;
+        let code = QrCode::new(content.as_bytes()).map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("生成二维码失败")
+        })?;
+        let width = code.width();
+        let size = (width + QUIET * 2) * SCALE;
+        let mut pixels = vec![255u8; size * size];
+        for (index, color) in code.to_colors().into_iter().enumerate() {
+            if color != Color::Dark {
+                continue;
+            }
+            let (x, y) = ((index % width + QUIET) * SCALE, (index / width + QUIET) * SCALE);
+            for row in y..y + SCALE {
+                pixels[row * size + x..row * size + x + SCALE].fill(0);
+            }
+        }
+        let mut image = Vec::new();
+        let mut encoder = png::Encoder::new(&mut image, size as u32, size as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("生成二维码失败")
+            })?;
+        Ok(format!("data:image/png;base64,{}", STANDARD.encode(image)))
+    }
+
+    /// 记录已使用的时间步，拒绝重放
+    pub async fn consume_step(app_state: &AppState, uid: i64, step: i64) -> Result<bool, AppError> {
+        let consumed = app_state
+            .eval::<_, _, i64>(&STEP_SCRIPT, &[RedisKey::mfa_step(uid)], &[step, PERIOD * (SKEW * 2 + 1)])
+            .await?;
+        Ok(consumed == 1)
+    }
+
+    /// 使用恢复码，未使用的恢复码才能更新成功
+    async fn consume_recovery_code(app_state: &AppState, uid: i64, code: &str) -> Result<bool, AppError> {
+        let result = app_state
+            .update_many::<MfaRecoveryCode>()
+            .col_expr(mfa_recovery_code::Column::UsedTime, Expr::value(Utc::now()))
+            .filter(mfa_recovery_code::Column::UserId.eq(uid))
+            .filter(mfa_recovery_code::Column::CodeHash.eq(Self::hash_recovery_code(code)))
+            .filter(mfa_recovery_code::Column::UsedTime.is_null())
+            .exec(&app_state.db.connection)
+            .await?;
+        if result.rows_affected > 0 {
+            tracing::warn!(target: "audit", "用户{}使用恢复码登录", uid);
+        }
+        Ok(result.rows_affected > 0)
+    }
+
+    fn base32_encode(bytes: &[u8]) -> String {
+        let mut result = String::with_capacity(bytes.len().div_ceil(5) * 8);
+        let (mut buffer, mut bits) = (0u32, 0);
+        for byte in bytes {
+            buffer = (buffer << 8) | *byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                result.push(BASE32[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+        }
+        if bits > 0 {
+            result.push(BASE32[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+        result
+    }
+
+    /// base32解码，忽略大小写、空白及填充
+    fn base32_decode(text: &str) -> Option<Vec<u8>> {
+        let mut result = Vec::with_capacity(text.len() * 5 / 8);
+        let (mut buffer, mut bits) = (0u32, 0);
+        for ch in text.bytes().filter(|ch| *ch != b'=' && !ch.is_ascii_whitespace()) {
+            let value = BASE32.iter().position(|c| *c == ch.to_ascii_uppercase())? as u32;
+            buffer = (buffer << 5) | value;
+            bits += 5;
+            if bits >= 8 {
+                bits -= 8;
+                result.push((buffer >> bits) as u8);
+            }
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::mfa::MfaUtils;
+
+    // RFC 6238附录B的SHA1测试密钥
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn base32_roundtrip() {
+        assert_eq!(MfaUtils::base32_encode(b"12345678901234567890"), SECRET);
+        assert_eq!(MfaUtils::base32_decode(SECRET).unwrap(), b"12345678901234567890");
+        assert_eq!(MfaUtils::base32_decode("gezd gnbv====").unwrap(), b"12345");
+        assert!(MfaUtils::base32_decode("GEZ1").is_none());
+        let secret = MfaUtils::secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(MfaUtils::base32_decode(&secret).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn rfc6238_vectors() {
+        let key = b"12345678901234567890";
+        // 附录B为8位验证码，6位取其末6位
+        for (timestamp, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ] {
+            assert_eq!(MfaUtils::hotp(key, (timestamp / 30) as u64), code);
+            assert_eq!(MfaUtils::verify_totp(SECRET, code, timestamp), Some(timestamp / 30));
+        }
+    }
+
+    #[test]
+    fn totp_skew() {
+        let code = MfaUtils::hotp(b"12345678901234567890", 1000);
+        assert_eq!(MfaUtils::verify_totp(SECRET, &code, 999 * 30), Some(1000));
+        assert_eq!(MfaUtils::verify_totp(SECRET, &code, 1001 * 30 + 29), Some(1000));
+        assert_eq!(MfaUtils::verify_totp(SECRET, &code, 1002 * 30), None);
+        assert_eq!(MfaUtils::verify_totp(SECRET, "12a456", 1000 * 30), None);
+    }
+
+    #[test]
+    fn recovery_code_hash() {
+        let codes = MfaUtils::recovery_codes();
+        assert_eq!(codes.len(), 10);
+        assert!(codes.iter().all(|code| code.len() == 11 && code.as_bytes()[5] == b'-'));
+        let code = &codes[0];
+        assert_eq!(
+            MfaUtils::hash_recovery_code(code),
+            MfaUtils::hash_recovery_code(&code.to_uppercase().replace('-', " "))
+        );
+        assert_ne!(MfaUtils::hash_recovery_code(code), MfaUtils::hash_recovery_code(&codes[1]));
+    }
+
+    #[test]
+    fn otpauth_uri() {
+        assert_eq!(
+            MfaUtils::uri("rato", "admin@x", "ABC"),
+            "otpauth://totp/rato:admin%40x?secret=ABC&issuer=rato&algorithm=SHA1&digits=6&period=30"
+        );
+        assert!(MfaUtils::qr_code(&MfaUtils::uri("rato", "admin", SECRET))
+            .unwrap()
+            .starts_with("data:image/png;base64,"));
+    }
+}
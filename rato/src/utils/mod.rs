@@ -2,7 +2,21 @@ use std::collections::HashSet;
 use std::hash::Hash;
 
 pub mod auth;
+pub mod captcha;
+pub mod file;
+pub mod image;
 pub mod jwt;
+pub mod login;
+pub mod mfa;
+pub mod oper_log;
+pub mod password;
+pub mod perm;
+pub mod proxy;
+pub mod scope;
+pub mod session;
+pub mod tenant;
+pub mod tree;
+pub mod upload;
 
 /// 通用工具类
 pub struct Utils;
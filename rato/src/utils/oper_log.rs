@@ -0,0 +1,172 @@
+use crate::core::result::ClientInfo;
+use crate::entity::oper_log;
+use crate::entity::prelude::OperLog;
+use crate::state::AppState;
+use crate::utils::tenant::TenantUtils;
+use async_trait::async_trait;
+use axum::extract::Request;
+use chrono::{DateTime, Utc};
+use rato_core::oper_log::{OperLogLayer, OperLogOptions, OperLogWriter, OperRecord};
+use sea_orm::{DatabaseConnection, EntityTrait, Set};
+use std::time::Duration;
+
+/// 操作日志中需脱敏的字段，字段名完全相同时脱敏，如两步验证的动态验证码及恢复码
+pub const SENSITIVE_FIELDS: [&str; 2] = ["code", "codes"];
+
+/// 操作日志中需脱敏的字段后缀，字段名的最后一个单词相同时脱敏，如`old_password`、`refreshToken`
+pub const SENSITIVE_SUFFIXES: [&str; 4] = ["password", "secret", "token", "captcha"];
+
+/// 请求开始时获取的信息。写入在后台任务中执行，租户需在请求所在的任务中获取
+pub struct OperExtra {
+    pub tenant_id: i64,
+    pub ip: String,
+}
+
+/// 操作日志写入数据库
+pub struct OperLogStore {
+    connection: DatabaseConnection,
+}
+
+impl OperLogStore {
+    /// 创建操作日志中间件
+    pub fn layer(app_state: &AppState) -> OperLogLayer<OperLogStore> {
+        let store = OperLogStore {
+            connection: app_state.db.connection.clone(),
+        };
+        OperLogLayer::new(
+            store,
+            OperLogOptions {
+                batch_size: app_state.env.oper_log_batch_size,
+                interval: Duration::from_secs(app_state.env.oper_log_interval),
+                sensitive: SENSITIVE_FIELDS.iter().map(|field| field.to_string()).collect(),
+                sensitive_suffixes: SENSITIVE_SUFFIXES.iter().map(|suffix| suffix.to_string()).collect(),
+                ..Default::default()
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl OperLogWriter for OperLogStore {
+    type Extra = OperExtra;
+
+    fn prepare(&self, req: &Request) -> Self::Extra {
+        OperExtra {
+            tenant_id: TenantUtils::current(),
+            ip: ClientInfo::resolve(req.headers(), req.extensions()).ip,
+        }
+    }
+
+    async fn write(&self, records: Vec<OperRecord<Self::Extra>>) {
+        let count = records.len();
+        let logs = records.into_iter().map(|record| oper_log::ActiveModel {
+            tenant_id: Set(record.extra.tenant_id),
+            user_id: Set(record.user_id),
+            account: Set(record.account),
+            method: Set(record.method),
+            path: Set(record.path),
+            perm: Set(record.perm),
+            query: Set(record.query),
+            params: Set(record.params),
+            status: Set(record.status as i32),
+            code: Set(record.code.map(|code| code as i32)),
+            message: Set(record.message.map(|message| message.chars().take(255).collect())),
+            ip: Set(record.extra.ip),
+            latency: Set(record.latency as i64),
+            bypass: Set(record.bypass),
+            create_time: Set(DateTime::<Utc>::from(record.time)),
+            ..Default::default()
+        });
+        if let Err(e) = OperLog::insert_many(logs).exec(&self.connection).await {
+            tracing::error!("写入操作日志失败，丢弃{}条：{:?}", count, e);
+        }
+    }
+}
+
+/// 操作日志导出工具类
+pub struct OperLogUtils;
+
+impl OperLogUtils {
+    /// 导出为CSV，首行为表头。带BOM以便Excel识别UTF-8编码
+    pub fn csv(logs: &[oper_log::Model]) -> String {
+        let mut csv = String::from("\u{feff}id,操作人id,操作人账号,请求方法,请求路径,权限,查询字符串,请求参数,HTTP状态码,业务状态码,结果说明,ip,耗时(ms),跳过权限校验,操作时间\n");
+        for log in logs {
+            let row = [
+                log.uid.to_string(),
+                log.user_id.map(|id| id.to_string()).unwrap_or_default(),
+                log.account.clone().unwrap_or_default(),
+                log.method.clone(),
+                log.path.clone(),
+                log.perm.clone().unwrap_or_default(),
+                log.query.clone().unwrap_or_default(),
+                log.params.clone().unwrap_or_default(),
+                log.status.to_string(),
+                log.code.map(|code| code.to_string()).unwrap_or_default(),
+                log.message.clone().unwrap_or_default(),
+                log.ip.clone(),
+                log.latency.to_string(),
+                if log.bypass { "是" } else { "否" }.to_string(),
+                log.create_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ];
+            let row = row.iter().map(|field| Self::csv_field(field)).collect::<Vec<_>>();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// 含逗号、引号或换行的字段用引号包裹，引号转义为两个引号。
+    /// 以`=`、`+`、`-`、`@`、制表符或回车开头的字段前加`'`，防止被表格软件当作公式执行
+    fn csv_field(field: &str) -> String {
+        let field = match field.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+            true => format!("'{}", field),
+            false => field.to_string(),
+        };
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::oper_log::{OperLogUtils, SENSITIVE_FIELDS, SENSITIVE_SUFFIXES};
+    use rato_core::oper_log::{redact_body, redact_query, OperLogOptions};
+
+    fn options() -> OperLogOptions {
+        OperLogOptions {
+            sensitive: SENSITIVE_FIELDS.iter().map(|field| field.to_string()).collect(),
+            sensitive_suffixes: SENSITIVE_SUFFIXES.iter().map(|suffix| suffix.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn redact() {
+        let body = br#"{"account":"admin","password":"123456","user":{"oldPassword":"a","name":"b"},"codes":[{"code":"1"}]}"#;
+        assert_eq!(
+            redact_body(body, &options()),
+            r#"{"account":"admin","codes":"******","password":"******","user":{"name":"b","oldPassword":"******"}}"#
+        );
+        assert_eq!(redact_body(b"not json", &options()), "");
+        assert_eq!(redact_query("uid=1&token=abc&name=x", &options()), "uid=1&token=******&name=x");
+        // 按完整字段名或最后一个单词匹配，不区分大小写
+        let body = br#"{"dept_code":"d1","zipcode":"100000","Code":"1","refreshToken":"a","MFA_SECRET":"b","tokenType":"c","passwordHint":"d"}"#;
+        assert_eq!(
+            redact_body(body, &options()),
+            r#"{"Code":"******","MFA_SECRET":"******","dept_code":"d1","passwordHint":"d","refreshToken":"******","tokenType":"c","zipcode":"100000"}"#
+        );
+    }
+
+    #[test]
+    fn csv_field() {
+        assert_eq!(OperLogUtils::csv_field("admin"), "admin");
+        assert_eq!(OperLogUtils::csv_field("a,b"), "\"a,b\"");
+        assert_eq!(OperLogUtils::csv_field(r#"{"a":"b"}"#), r#""{""a"":""b""}""#);
+        assert_eq!(OperLogUtils::csv_field("=1+1"), "'=1+1");
+        assert_eq!(OperLogUtils::csv_field("\t=1+1"), "'\t=1+1");
+        assert_eq!(OperLogUtils::csv_field("\r=1+1"), "\"'\r=1+1\"");
+    }
+}
@@ -0,0 +1,245 @@
+use crate::core::error::AppError;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use serde::Deserialize;
+use std::sync::OnceLock;
+use subtle::ConstantTimeEq;
+
+/// 账号不存在时用于校验的哈希，使不存在的账号与密码错误的耗时一致
+static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+/// 密码哈希算法。配置为其他值时启动失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordAlgorithm {
+    Argon2id,
+    Bcrypt,
+}
+
+/// 密码哈希配置
+#[derive(Debug, Clone)]
+pub struct PasswordConfig {
+    pub algorithm: PasswordAlgorithm,
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+    pub bcrypt_cost: u32,
+}
+
+/// 密码校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordVerify {
+    // 密码错误
+    Invalid,
+    // 密码正确
+    Valid,
+    // 密码正确，但存储格式或参数已过时（如明文密码），需重新哈希
+    NeedsRehash,
+}
+
+/// 密码工具类。使用PHC字符串格式保存，默认argon2id，兼容bcrypt及历史明文密码
+pub struct PasswordUtils;
+
+impl PasswordUtils {
+    /// 启动时校验哈希参数并生成账号不存在时使用的哈希
+    pub fn init(config: &PasswordConfig) -> Result<(), AppError> {
+        let dummy = Self::hash(&uuid::Uuid::new_v4().to_string(), config)?;
+        let _ = DUMMY_HASH.set(dummy);
+        Ok(())
+    }
+
+    /// 在阻塞线程池中哈希密码，避免占用异步运行时的工作线程
+    pub async fn hash_async(password: &str, config: &PasswordConfig) -> Result<String, AppError> {
+        let (password, config) = (password.to_string(), config.clone());
+        tokio::task::spawn_blocking(move || Self::hash(&password, &config))
+            .await
+            .map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("密码加密失败")
+            })?
+    }
+
+    /// 在阻塞线程池中校验密码。账号不存在时传入None，按同样的耗时校验后返回密码错误
+    pub async fn verify_async(password: &str, hashed: Option<&str>, config: &PasswordConfig) -> PasswordVerify {
+        let exists = hashed.is_some();
+        let hashed = hashed
+            .or(DUMMY_HASH.get().map(String::as_str))
+            .unwrap_or_default()
+            .to_string();
+        let (password, config) = (password.to_string(), config.clone());
+        let verify = tokio::task::spawn_blocking(move || Self::verify(&password, &hashed, &config))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("{:?}", e);
+                PasswordVerify::Invalid
+            });
+        if exists {
+            verify
+        } else {
+            PasswordVerify::Invalid
+        }
+    }
+
+    /// 按配置的算法哈希密码
+    pub fn hash(password: &str, config: &PasswordConfig) -> Result<String, AppError> {
+        match config.algorithm {
+            PasswordAlgorithm::Argon2id => {
+                let salt = SaltString::generate(&mut OsRng);
+                Self::argon2(config)?
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| {
+                        tracing::error!("{:?}", e);
+                        AppError::Other("密码加密失败")
+                    })
+            }
+            PasswordAlgorithm::Bcrypt => bcrypt::hash(password, config.bcrypt_cost).map_err(|e| {
+                tracing::error!("{:?}", e);
+                AppError::Other("密码加密失败")
+            }),
+        }
+    }
+
+    /// 常量时间校验密码，并判断是否需要按当前配置重新哈希
+    pub fn verify(password: &str, hashed: &str, config: &PasswordConfig) -> PasswordVerify {
+        if hashed.starts_with("$argon2") {
+            let parsed = match PasswordHash::new(hashed) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    tracing::error!("{:?}", e);
+                    return PasswordVerify::Invalid;
+                }
+            };
+            if Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_err()
+            {
+                return PasswordVerify::Invalid;
+            }
+            let outdated = config.algorithm != PasswordAlgorithm::Argon2id
+                || parsed.algorithm != argon2::ARGON2ID_IDENT
+                || Params::try_from(&parsed).map_or(true, |params| {
+                    params.m_cost() != config.memory_cost
+                        || params.t_cost() != config.time_cost
+                        || params.p_cost() != config.parallelism
+                });
+            return Self::result(outdated);
+        }
+        if hashed.starts_with("$2") {
+            if !bcrypt::verify(password, hashed).unwrap_or(false) {
+                return PasswordVerify::Invalid;
+            }
+            let outdated = config.algorithm != PasswordAlgorithm::Bcrypt
+                || hashed.get(4..6).and_then(|cost| cost.parse::<u32>().ok())
+                    != Some(config.bcrypt_cost);
+            return Self::result(outdated);
+        }
+        // 历史明文密码，校验通过后需升级为哈希
+        if !hashed.is_empty() && bool::from(password.as_bytes().ct_eq(hashed.as_bytes())) {
+            PasswordVerify::NeedsRehash
+        } else {
+            PasswordVerify::Invalid
+        }
+    }
+
+    fn argon2(config: &PasswordConfig) -> Result<Argon2<'static>, AppError> {
+        let params = Params::new(
+            config.memory_cost,
+            config.time_cost,
+            config.parallelism,
+            None,
+        )
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("密码哈希参数错误")
+        })?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    fn result(outdated: bool) -> PasswordVerify {
+        if outdated {
+            PasswordVerify::NeedsRehash
+        } else {
+            PasswordVerify::Valid
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::password::{PasswordAlgorithm, PasswordConfig, PasswordUtils, PasswordVerify};
+
+    fn config(algorithm: PasswordAlgorithm) -> PasswordConfig {
+        PasswordConfig {
+            algorithm,
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+            bcrypt_cost: 4,
+        }
+    }
+
+    #[test]
+    fn argon2_hash() {
+        let config = config(PasswordAlgorithm::Argon2id);
+        let hashed = PasswordUtils::hash("123456", &config).unwrap();
+        assert!(hashed.starts_with("$argon2id$"));
+        assert_eq!(PasswordUtils::verify("123456", &hashed, &config), PasswordVerify::Valid);
+        assert_eq!(PasswordUtils::verify("1234567", &hashed, &config), PasswordVerify::Invalid);
+        let mut stronger = config.clone();
+        stronger.time_cost = 2;
+        assert_eq!(
+            PasswordUtils::verify("123456", &hashed, &stronger),
+            PasswordVerify::NeedsRehash
+        );
+    }
+
+    #[test]
+    fn bcrypt_hash() {
+        let bcrypt = config(PasswordAlgorithm::Bcrypt);
+        let hashed = PasswordUtils::hash("123456", &bcrypt).unwrap();
+        assert!(hashed.starts_with("$2b$04$"));
+        assert_eq!(PasswordUtils::verify("123456", &hashed, &bcrypt), PasswordVerify::Valid);
+        assert_eq!(PasswordUtils::verify("654321", &hashed, &bcrypt), PasswordVerify::Invalid);
+        let argon2 = config(PasswordAlgorithm::Argon2id);
+        assert_eq!(
+            PasswordUtils::verify("123456", &hashed, &argon2),
+            PasswordVerify::NeedsRehash
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_async() {
+        let config = config(PasswordAlgorithm::Argon2id);
+        PasswordUtils::init(&config).unwrap();
+        let hashed = PasswordUtils::hash_async("123456", &config).await.unwrap();
+        assert_eq!(
+            PasswordUtils::verify_async("123456", Some(&hashed), &config).await,
+            PasswordVerify::Valid
+        );
+        assert_eq!(
+            PasswordUtils::verify_async("123456", None, &config).await,
+            PasswordVerify::Invalid
+        );
+    }
+
+    #[test]
+    fn algorithm() {
+        let parse = |value: &str| serde_json::from_value::<PasswordAlgorithm>(serde_json::json!(value));
+        assert_eq!(parse("argon2id").unwrap(), PasswordAlgorithm::Argon2id);
+        assert_eq!(parse("bcrypt").unwrap(), PasswordAlgorithm::Bcrypt);
+        assert!(parse("md5").is_err());
+    }
+
+    #[test]
+    fn plaintext_upgrade() {
+        let config = config(PasswordAlgorithm::Argon2id);
+        assert_eq!(
+            PasswordUtils::verify("123456", "123456", &config),
+            PasswordVerify::NeedsRehash
+        );
+        assert_eq!(PasswordUtils::verify("12345", "123456", &config), PasswordVerify::Invalid);
+        assert_eq!(PasswordUtils::verify("", "123456", &config), PasswordVerify::Invalid);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::entity::prelude::{Menu, Role, RoleDept, RoleMenu, RoleParent, User, UserRole};
+use crate::entity::{menu, role, role_dept, role_menu, user, user_role};
+use crate::entity::user::{LoginUser, UserPerm};
+use crate::state::AppState;
+use crate::utils::auth::{AuthUtils, PermMatcher};
+use crate::utils::scope::{DataScope, DataScopeType};
+use crate::utils::Utils;
+use rato_core::oper_log::OperContext;
+use rato_core::redis::RedisPool;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, QueryFilter, QuerySelect,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 权限匹配器缓存的最大数量
+const MAX_MATCHERS: usize = 10_000;
+
+/// 权限匹配器缓存的有效期
+const MATCHER_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// 缓存的权限匹配器
+pub struct CachedMatcher {
+    // 构建时的权限版本号
+    version: i64,
+    matcher: Arc<PermMatcher>,
+    built: Instant,
+}
+
+/// 用户角色及权限工具类。权限从数据库加载后按全局版本号缓存，角色、菜单及其关联变更时使版本号失效
+pub struct PermUtils;
+
+impl PermUtils {
+    /// 获取用户角色及权限。缓存版本号与全局版本号一致时直接使用缓存，否则重新加载
+    pub async fn resolve(app_state: &AppState, uid: i64) -> Result<UserPerm, AppError> {
+        // 先读取版本号再加载，加载期间发生的变更会在下一次请求时重新加载
+        let version = Self::version(app_state).await?;
+        let key = RedisKey::user_perm(uid);
+        if app_state.exists(&key).await.is_ok() {
+            let cached = app_state.cached::<UserPerm>(&key).await?;
+            if cached.version == version {
+                return Ok(cached);
+            }
+        }
+        let (roles, perms, data_scope) = Self::load(app_state, uid).await?;
+        let declared = Self::declared(app_state).await?;
+        let user_perm = UserPerm {
+            version,
+            roles,
+            perms,
+            data_scope,
+            declared,
+        };
+        app_state
+            .set_ex(&key, user_perm.clone(), app_state.env.refresh_token_expire as u64)
+            .await?;
+        Ok(user_perm)
+    }
+
+    /// 用户的权限匹配器。同一权限版本内只构建一次，之后的请求直接复用。
+    /// 缓存超过有效期后重新构建，缓存数量达到上限时先清理过期的，仍超出时全部清空
+    pub fn matcher(app_state: &AppState, uid: i64, user_perm: &UserPerm) -> Arc<PermMatcher> {
+        if let Some(cached) = app_state.perm_matchers.read().unwrap().get(&uid) {
+            if cached.version == user_perm.version && cached.built.elapsed() < MATCHER_TTL {
+                return cached.matcher.clone();
+            }
+        }
+        let matcher = Arc::new(
+            PermMatcher::new(&user_perm.perms, &user_perm.declared).basic(app_state.env.basic_perms_enabled),
+        );
+        let mut matchers = app_state.perm_matchers.write().unwrap();
+        if matchers.len() >= MAX_MATCHERS && !matchers.contains_key(&uid) {
+            matchers.retain(|_, cached| cached.built.elapsed() < MATCHER_TTL);
+            if matchers.len() >= MAX_MATCHERS {
+                matchers.clear();
+            }
+        }
+        matchers.insert(
+            uid,
+            CachedMatcher {
+                version: user_perm.version,
+                matcher: matcher.clone(),
+                built: Instant::now(),
+            },
+        );
+        matcher
+    }
+
+    /// 是否为超级管理员。未启用超级管理员时始终为false
+    pub fn is_super_admin(app_state: &AppState, login_user: &LoginUser) -> bool {
+        let env = &app_state.env;
+        env.super_admin_enabled && login_user.has_any_role(vec![env.super_admin_role.clone()])
+    }
+
+    /// 超级管理员跳过权限或数据范围校验时调用，记录审计日志并写入操作日志
+    pub fn audit_bypass(context: Option<&OperContext>, login_user: &LoginUser, detail: std::fmt::Arguments) {
+        tracing::info!(target: "audit", "超级管理员跳过权限校验，用户：{}，{}", login_user.uid, detail);
+        if let Some(context) = context {
+            context.bypass();
+        }
+    }
+
+    /// 角色、菜单、角色菜单关联或用户角色关联变更后调用，所有用户的权限缓存随即失效
+    pub async fn invalidate(app_state: &AppState) -> Result<(), AppError> {
+        let version = app_state.incr(RedisKey::perm_version()).await?;
+        tracing::info!("权限版本号更新：{}", version);
+        Ok(())
+    }
+
+    /// 当前全局权限版本号，未初始化时为0
+    async fn version(app_state: &AppState) -> Result<i64, AppError> {
+        let key = RedisKey::perm_version();
+        if app_state.exists(&key).await.is_err() {
+            return Ok(0);
+        }
+        app_state.get::<_, i64>(&key).await
+    }
+
+    /// 从数据库加载用户角色、菜单权限及数据范围，包含通过角色继承获得的角色及权限
+    async fn load(
+        app_state: &AppState,
+        uid: i64,
+    ) -> Result<(Vec<String>, Vec<String>, DataScope), AppError> {
+        let connection = &app_state.db.connection;
+        let user = app_state.find_by_id::<User>(uid)
+            .one(connection)
+            .await?
+            .ok_or(AppError::Other("未找到用户信息"))?;
+        // 获取用户直接拥有的角色
+        let direct = app_state.find::<UserRole>()
+            .filter(user_role::Column::UserId.eq(user.uid))
+            .select_only()
+            .column(user_role::Column::RoleId)
+            .into_tuple::<i64>()
+            .all(connection)
+            .await?;
+        // 沿继承关系展开全部角色
+        let closure = Self::ancestors(&Self::role_parents(app_state, connection).await?, direct);
+        let roles = app_state.find::<Role>()
+            .filter(role::Column::Uid.is_in(closure))
+            .all(connection)
+            .await?;
+        // 获取用户菜单权限，关联表及菜单均按租户过滤
+        let menu_ids = app_state.find::<RoleMenu>()
+            .filter(role_menu::Column::RoleId.is_in(roles.iter().map(|role| role.uid)))
+            .select_only()
+            .column(role_menu::Column::MenuId)
+            .into_tuple::<i64>()
+            .all(connection)
+            .await?;
+        let menus = app_state.find::<Menu>()
+            .filter(menu::Column::Uid.is_in(menu_ids))
+            .all(connection)
+            .await?;
+        let data_scope = Self::data_scope(app_state, connection, &user, &roles).await?;
+        let roles = roles.iter().map(|role| role.value.clone()).collect();
+        // 菜单权限去重
+        let perms = Utils::dedup(vec![menus], |menu| menu.value.clone()).await;
+        Ok((roles, perms, data_scope))
+    }
+
+    /// 租户菜单中声明的全部权限值，用于判断较短的权限包含哪些下级权限
+    async fn declared(app_state: &AppState) -> Result<Vec<String>, AppError> {
+        let values = app_state.find::<Menu>()
+            .select_only()
+            .column(menu::Column::Value)
+            .distinct()
+            .into_tuple::<String>()
+            .all(&app_state.db.connection)
+            .await?;
+        Ok(values)
+    }
+
+    /// 合并全部角色的数据范围
+    async fn data_scope<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+        user: &user::Model,
+        roles: &[role::Model],
+    ) -> Result<DataScope, AppError> {
+        let mut scope = DataScope {
+            uid: user.uid,
+            ..Default::default()
+        };
+        let mut custom = Vec::new();
+        for role in roles {
+            match DataScopeType::from_str(&role.data_scope) {
+                Some(DataScopeType::All) => return Ok(DataScope::all(user.uid)),
+                Some(DataScopeType::Dept) => scope.dept_ids.extend(user.dept_id),
+                Some(DataScopeType::Custom) => custom.push(role.uid),
+                // 未知的数据范围按本人处理
+                Some(DataScopeType::Own) | None => scope.own = true,
+            }
+        }
+        if !custom.is_empty() {
+            let dept_ids = app_state.find::<RoleDept>()
+                .filter(role_dept::Column::RoleId.is_in(custom))
+                .all(connection)
+                .await?;
+            scope.dept_ids.extend(dept_ids.into_iter().map(|role_dept| role_dept.dept_id));
+        }
+        scope.dept_ids.sort_unstable();
+        scope.dept_ids.dedup();
+        Ok(scope)
+    }
+
+    /// 全部角色继承关系，角色id到父角色id列表的映射
+    pub async fn role_parents<C: ConnectionTrait>(
+        app_state: &AppState,
+        connection: &C,
+    ) -> Result<HashMap<i64, Vec<i64>>, AppError> {
+        let edges = app_state.find::<RoleParent>().all(connection).await?;
+        let mut parents: HashMap<i64, Vec<i64>> = HashMap::new();
+        for edge in edges {
+            parents.entry(edge.role_id).or_default().push(edge.parent_id);
+        }
+        Ok(parents)
+    }
+
+    /// 角色及其全部祖先角色，按广度优先顺序返回且不重复。继承关系中存在环时也能结束
+    pub fn ancestors<I>(parents: &HashMap<i64, Vec<i64>>, roles: I) -> Vec<i64>
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        let mut visited = HashSet::new();
+        let mut queue = roles.into_iter().collect::<VecDeque<_>>();
+        let mut closure = Vec::new();
+        while let Some(role) = queue.pop_front() {
+            if !visited.insert(role) {
+                continue;
+            }
+            closure.push(role);
+            if let Some(items) = parents.get(&role) {
+                queue.extend(items.iter().copied());
+            }
+        }
+        closure
+    }
+
+    /// 将角色的父角色设置为new_parents后，继承关系是否成环
+    pub fn has_cycle(parents: &HashMap<i64, Vec<i64>>, role: i64, new_parents: &[i64]) -> bool {
+        let mut parents = parents.clone();
+        parents.insert(role, new_parents.to_vec());
+        Self::ancestors(&parents, new_parents.iter().copied()).contains(&role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::perm::PermUtils;
+    use std::collections::HashMap;
+
+    #[test]
+    fn role_closure() {
+        // admin(1) -> operator(2) -> auditor(3)，api(4) -> auditor(3)
+        let parents = HashMap::from([(1, vec![2]), (2, vec![3]), (4, vec![3])]);
+        assert_eq!(PermUtils::ancestors(&parents, [1]), vec![1, 2, 3]);
+        assert_eq!(PermUtils::ancestors(&parents, [4, 2]), vec![4, 2, 3]);
+        assert_eq!(PermUtils::ancestors(&parents, [3]), vec![3]);
+    }
+
+    #[test]
+    fn role_cycle() {
+        let parents = HashMap::from([(1, vec![2]), (2, vec![3])]);
+        assert!(PermUtils::has_cycle(&parents, 3, &[1]));
+        assert!(PermUtils::has_cycle(&parents, 2, &[2]));
+        assert!(!PermUtils::has_cycle(&parents, 3, &[4]));
+        // 替换原有父角色后不再成环
+        assert!(!PermUtils::has_cycle(&parents, 2, &[4]));
+        assert!(!PermUtils::has_cycle(&parents, 1, &[3]));
+    }
+}
@@ -0,0 +1,120 @@
+use std::net::IpAddr;
+
+/// 信任的反向代理。仅连接地址属于信任的代理时才读取代理转发请求头，
+/// 否则客户端可伪造请求头绕过按ip的登录限制及日志记录
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    // 网段地址及前缀长度
+    nets: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// 解析ip或CIDR，多个以逗号分隔
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let nets = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                let (ip, prefix) = item.split_once('/').unwrap_or((item, ""));
+                let ip = ip.parse::<IpAddr>().map_err(|_| format!("无效的代理地址：{}", item))?;
+                let max = if ip.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    "" => max,
+                    prefix => prefix
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|prefix| *prefix <= max)
+                        .ok_or(format!("无效的代理地址：{}", item))?,
+                };
+                Ok((ip, prefix))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(TrustedProxies { nets })
+    }
+
+    /// 是否为信任的代理
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.nets.iter().any(|(net, prefix)| match (net, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                Self::masked(u32::from(*net) as u128, *prefix, 32)
+                    == Self::masked(u32::from(ip) as u128, *prefix, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                Self::masked(u128::from(*net), *prefix, 128) == Self::masked(u128::from(ip), *prefix, 128)
+            }
+            _ => false,
+        })
+    }
+
+    /// 解析客户端ip。连接地址不是信任的代理时直接使用连接地址；
+    /// 否则从右向左跳过X-Forwarded-For中信任的代理，取第一个非代理地址，没有X-Forwarded-For时取X-Real-IP
+    pub fn client_ip(&self, peer: Option<IpAddr>, forwarded_for: Option<&str>, real_ip: Option<&str>) -> Option<IpAddr> {
+        let peer = peer.map(|peer| peer.to_canonical());
+        if peer.is_some_and(|peer| !self.contains(&peer)) {
+            return peer;
+        }
+        if let Some(forwarded_for) = forwarded_for {
+            let hops = forwarded_for
+                .split(',')
+                .map(|ip| ip.trim().parse::<IpAddr>().ok())
+                .collect::<Option<Vec<_>>>();
+            if let Some(hops) = hops.filter(|hops| !hops.is_empty()) {
+                return hops
+                    .iter()
+                    .rev()
+                    .find(|ip| !self.contains(ip))
+                    .or(hops.first())
+                    .copied();
+            }
+        }
+        real_ip
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+            .or(peer)
+    }
+
+    fn masked(value: u128, prefix: u8, bits: u8) -> u128 {
+        match prefix {
+            0 => 0,
+            prefix => value >> (bits - prefix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::proxy::TrustedProxies;
+    use std::net::IpAddr;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn contains() {
+        let proxies = TrustedProxies::parse("127.0.0.1, 10.0.0.0/8, ::1").unwrap();
+        assert!(proxies.contains(&ip("127.0.0.1")));
+        assert!(proxies.contains(&ip("10.1.2.3")));
+        assert!(proxies.contains(&ip("::ffff:10.1.2.3")));
+        assert!(proxies.contains(&ip("::1")));
+        assert!(!proxies.contains(&ip("11.0.0.1")));
+        assert!(TrustedProxies::parse("10.0.0.0/33").is_err());
+        assert!(TrustedProxies::parse("localhost").is_err());
+        assert!(TrustedProxies::parse("0.0.0.0/0").unwrap().contains(&ip("8.8.8.8")));
+    }
+
+    #[test]
+    fn client_ip() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8").unwrap();
+        let peer = Some(ip("10.0.0.1"));
+        // 连接地址不是信任的代理时忽略请求头
+        assert_eq!(proxies.client_ip(Some(ip("8.8.8.8")), Some("1.1.1.1"), Some("2.2.2.2")), Some(ip("8.8.8.8")));
+        assert_eq!(proxies.client_ip(peer, Some("1.1.1.1, 10.0.0.2"), None), Some(ip("1.1.1.1")));
+        // 客户端自行添加的地址位于最左侧，不予采信
+        assert_eq!(proxies.client_ip(peer, Some("6.6.6.6, 1.1.1.1, 10.0.0.2"), None), Some(ip("1.1.1.1")));
+        assert_eq!(proxies.client_ip(peer, None, Some("2.2.2.2")), Some(ip("2.2.2.2")));
+        assert_eq!(proxies.client_ip(peer, Some("bad"), None), peer);
+        assert_eq!(TrustedProxies::default().client_ip(peer, Some("1.1.1.1"), None), peer);
+    }
+}
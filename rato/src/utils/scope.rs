@@ -0,0 +1,148 @@
+use crate::entity::user;
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter, Select};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// 角色数据范围类型
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DataScopeType {
+    // 全部数据
+    All,
+    // 本人创建的数据
+    Own,
+    // 本部门用户创建的数据
+    Dept,
+    // 指定部门用户创建的数据
+    Custom,
+}
+
+impl DataScopeType {
+    pub fn from_str(str: &str) -> Option<DataScopeType> {
+        match str {
+            "all" => Some(DataScopeType::All),
+            "self" => Some(DataScopeType::Own),
+            "dept" => Some(DataScopeType::Dept),
+            "custom" => Some(DataScopeType::Custom),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DataScopeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let str = match self {
+            DataScopeType::All => "all",
+            DataScopeType::Own => "self",
+            DataScopeType::Dept => "dept",
+            DataScopeType::Custom => "custom",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+/// 用户的数据范围，由其全部角色的数据范围合并而成，满足任一范围即可见
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DataScope {
+    pub uid: i64,
+    // 可见全部数据
+    pub all: bool,
+    // 可见本人创建的数据
+    pub own: bool,
+    // 可见这些部门的用户创建的数据
+    pub dept_ids: Vec<i64>,
+}
+
+impl DataScope {
+    /// 不受限制的数据范围
+    pub fn all(uid: i64) -> Self {
+        DataScope {
+            uid,
+            all: true,
+            ..Default::default()
+        }
+    }
+
+    /// 按创建人过滤的查询条件。没有任何数据范围时不匹配任何数据
+    pub fn condition<E: CreatorColumn>(&self) -> Condition {
+        if self.all {
+            return Condition::all();
+        }
+        let column = E::creator_column();
+        let mut condition = Condition::any();
+        if self.own {
+            condition = condition.add(column.eq(self.uid));
+        }
+        if !self.dept_ids.is_empty() {
+            condition = condition.add(
+                column.in_subquery(
+                    Query::select()
+                        .column(user::Column::Uid)
+                        .from(user::Entity)
+                        .and_where(user::Column::DeptId.is_in(self.dept_ids.clone()))
+                        .to_owned(),
+                ),
+            );
+        }
+        if condition.is_empty() {
+            return Condition::all().add(Expr::val(1).eq(0));
+        }
+        condition
+    }
+}
+
+/// 带有创建人字段的实体，可按数据范围过滤
+pub trait CreatorColumn: EntityTrait {
+    fn creator_column() -> Self::Column;
+}
+
+/// 数据范围查询扩展
+pub trait ScopeSelect {
+    /// 按调用者的数据范围过滤
+    fn scoped(self, scope: &DataScope) -> Self;
+}
+
+impl<E: CreatorColumn> ScopeSelect for Select<E> {
+    fn scoped(self, scope: &DataScope) -> Self {
+        if scope.all {
+            return self;
+        }
+        self.filter(scope.condition::<E>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entity::prelude::User;
+    use crate::utils::scope::{DataScope, ScopeSelect};
+    use sea_orm::{DbBackend, EntityTrait, QueryTrait};
+
+    fn sql(scope: &DataScope) -> String {
+        User::find().scoped(scope).build(DbBackend::MySql).to_string()
+    }
+
+    #[test]
+    fn scope_condition() {
+        assert!(!sql(&DataScope::all(7)).contains("WHERE"));
+        let own = DataScope {
+            uid: 7,
+            own: true,
+            ..Default::default()
+        };
+        assert!(sql(&own).ends_with("WHERE `t_user`.`creator_id` = 7"));
+        let dept = DataScope {
+            uid: 7,
+            own: true,
+            dept_ids: vec![3, 4],
+            ..Default::default()
+        };
+        assert!(sql(&dept).ends_with(
+            "WHERE `t_user`.`creator_id` = 7 OR `t_user`.`creator_id` IN (SELECT `uid` FROM `t_user` WHERE `t_user`.`dept_id` IN (3, 4))"
+        ));
+        let none = DataScope {
+            uid: 7,
+            ..Default::default()
+        };
+        assert!(sql(&none).ends_with("WHERE 1 = 0"));
+    }
+}
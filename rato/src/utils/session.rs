@@ -0,0 +1,131 @@
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::entity::prelude::User;
+use crate::entity::session::SessionInfo;
+use crate::entity::user::AccountState;
+use crate::state::AppState;
+use chrono::{Duration, Utc};
+use rato_core::redis::RedisPool;
+
+/// 最后活跃时间的更新间隔，避免每次请求都写缓存
+const TOUCH_INTERVAL: i64 = 60;
+
+/// 账号状态缓存有效期，单位秒
+const ACCOUNT_STATE_EXPIRE: u64 = 5 * 60;
+
+/// 登录会话工具类。会话以会话id为key保存，并按用户建立会话id集合索引
+pub struct SessionUtils;
+
+impl SessionUtils {
+    /// 保存会话，并加入用户的会话集合
+    pub async fn save(app_state: &AppState, session: &SessionInfo) -> Result<(), AppError> {
+        let expire = app_state.env.refresh_token_expire;
+        app_state
+            .set_ex(RedisKey::session(&session.sid), session.clone(), expire as u64)
+            .await?;
+        let key = RedisKey::user_sessions(session.uid);
+        app_state.sadd(&key, &session.sid).await?;
+        app_state.expire(&key, expire).await
+    }
+
+    /// 获取会话，不存在或已过期返回None
+    pub async fn get(app_state: &AppState, sid: &str) -> Result<Option<SessionInfo>, AppError> {
+        let key = RedisKey::session(sid);
+        if app_state.exists(&key).await.is_err() {
+            return Ok(None);
+        }
+        app_state.cached::<SessionInfo>(&key).await.map(Some)
+    }
+
+    /// 更新最后活跃时间，保留会话原有过期时间
+    pub async fn touch(app_state: &AppState, mut session: SessionInfo) -> Result<(), AppError> {
+        let now = Utc::now();
+        if now - session.last_seen < Duration::seconds(TOUCH_INTERVAL) {
+            return Ok(());
+        }
+        session.last_seen = now;
+        app_state
+            .set_keep_ttl(RedisKey::session(&session.sid), session)
+            .await
+    }
+
+    /// 用户的全部有效会话，按最后活跃时间倒序。顺带清理集合中已过期的会话id
+    pub async fn list(app_state: &AppState, uid: i64) -> Result<Vec<SessionInfo>, AppError> {
+        let key = RedisKey::user_sessions(uid);
+        let sids = app_state.smembers::<_, String>(&key).await?;
+        let mut sessions = Vec::with_capacity(sids.len());
+        for sid in sids {
+            match Self::get(app_state, &sid).await? {
+                Some(session) => sessions.push(session),
+                None => app_state.srem(&key, &sid).await?,
+            }
+        }
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen));
+        Ok(sessions)
+    }
+
+    /// 撤销用户的全部会话，返回撤销的会话数
+    pub async fn remove_all(app_state: &AppState, uid: i64) -> Result<usize, AppError> {
+        let sessions = Self::list(app_state, uid).await?;
+        for session in sessions.iter() {
+            Self::remove(app_state, uid, &session.sid).await?;
+        }
+        Ok(sessions.len())
+    }
+
+    /// 撤销用户除指定会话外的全部会话，返回撤销的会话数
+    pub async fn remove_others(app_state: &AppState, uid: i64, sid: &str) -> Result<usize, AppError> {
+        let mut count = 0;
+        for session in Self::list(app_state, uid).await?.iter().filter(|session| session.sid != sid) {
+            Self::remove(app_state, uid, &session.sid).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// 账号状态，优先使用缓存。用户不存在时返回None
+    pub async fn account(app_state: &AppState, uid: i64) -> Result<Option<AccountState>, AppError> {
+        let key = RedisKey::account_state(uid);
+        if app_state.exists(&key).await.is_ok() {
+            return app_state.cached::<AccountState>(&key).await.map(Some);
+        }
+        let state = app_state
+            .find_by_id::<User>(uid)
+            .one(&app_state.db.connection)
+            .await?
+            .map(|user| AccountState::from(&user));
+        if let Some(state) = &state {
+            app_state.set_ex(&key, state.clone(), ACCOUNT_STATE_EXPIRE).await?;
+        }
+        Ok(state)
+    }
+
+    /// 账号状态变更后更新缓存，已登录的会话在下一次请求时即按新状态校验
+    pub async fn set_account(app_state: &AppState, uid: i64, state: AccountState) -> Result<(), AppError> {
+        app_state
+            .set_ex(RedisKey::account_state(uid), state, ACCOUNT_STATE_EXPIRE)
+            .await
+    }
+
+    /// 校验会话所属账号的状态，不可用时撤销该会话并返回原因
+    pub async fn check_account(app_state: &AppState, session: &SessionInfo) -> Result<(), AppError> {
+        let reason = match Self::account(app_state, session.uid).await? {
+            Some(state) => state.inactive_reason(Utc::now()),
+            None => Some("未找到用户信息"),
+        };
+        match reason {
+            Some(reason) => {
+                Self::remove(app_state, session.uid, &session.sid).await?;
+                Err(AppError::Relogin(reason))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 撤销会话，同时撤销该会话的刷新令牌族
+    pub async fn remove(app_state: &AppState, uid: i64, sid: &str) -> Result<(), AppError> {
+        let _ = app_state.del(RedisKey::session(sid)).await;
+        let _ = app_state.del(RedisKey::refresh_family(sid)).await;
+        app_state.srem(RedisKey::user_sessions(uid), sid).await
+    }
+}
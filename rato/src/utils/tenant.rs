@@ -0,0 +1,195 @@
+use crate::core::constant::{RedisKey, TENANT_HEADER};
+use crate::core::error::AppError;
+use crate::entity::prelude::Tenant;
+use crate::entity::tenant;
+use crate::state::AppState;
+use crate::utils::jwt::JwtUtils;
+use axum::http::header::{AUTHORIZATION, HOST};
+use axum::http::HeaderMap;
+use rato_core::redis::RedisPool;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use std::future::Future;
+
+/// 平台租户id。未识别到租户的请求均属于平台租户，平台租户的超级管理员即平台管理员
+pub const PLATFORM_TENANT: i64 = 0;
+
+/// 租户缓存有效期，单位秒
+const TENANT_CACHE_EXPIRE: u64 = 5 * 60;
+
+tokio::task_local! {
+    static TENANT: i64;
+}
+
+/// 按租户隔离的实体
+pub trait TenantEntity: EntityTrait {
+    fn tenant_column() -> Self::Column;
+}
+
+/// 租户工具类。请求所属租户保存在task local中，同一请求内的查询及缓存key均按该租户隔离
+pub struct TenantUtils;
+
+impl TenantUtils {
+    /// 当前租户id，不在租户上下文中时为平台租户
+    pub fn current() -> i64 {
+        TENANT.try_with(|tenant_id| *tenant_id).unwrap_or(PLATFORM_TENANT)
+    }
+
+    /// 当前是否为平台租户
+    pub fn is_platform() -> bool {
+        Self::current() == PLATFORM_TENANT
+    }
+
+    /// 在指定租户下执行
+    pub async fn scope<F: Future>(tenant_id: i64, f: F) -> F::Output {
+        TENANT.scope(tenant_id, f).await
+    }
+
+    /// 解析请求所属租户。优先使用令牌中的租户，其次为请求头中的租户编码，最后按Host匹配租户域名。
+    /// 刷新令牌及两步验证的请求不携带访问令牌，由handler按[`TenantUtils::check`]校验令牌中记录的租户
+    pub async fn resolve(app_state: &AppState, headers: &HeaderMap) -> Result<i64, AppError> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        // 令牌无效时交由认证中间件处理，此处仅忽略
+        let claims = header(AUTHORIZATION.as_str())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| JwtUtils::decode(token, &app_state.jwt).ok());
+        let tenant = if let Some(claims) = claims {
+            if claims.tenant_id == PLATFORM_TENANT {
+                return Ok(PLATFORM_TENANT);
+            }
+            Self::get(app_state, claims.tenant_id).await?
+        } else if let Some(code) = header(TENANT_HEADER).filter(|code| !code.is_empty()) {
+            let tenant = Tenant::find()
+                .filter(tenant::Column::Code.eq(code))
+                .one(&app_state.db.connection)
+                .await?;
+            Some(tenant.ok_or(AppError::Other("未找到租户信息"))?)
+        } else if let Some(host) = header(HOST.as_str()) {
+            // 去掉端口号
+            let domain = host.rsplit_once(':').map_or(host, |(domain, _)| domain);
+            Tenant::find()
+                .filter(tenant::Column::Domain.eq(domain))
+                .one(&app_state.db.connection)
+                .await?
+        } else {
+            None
+        };
+        match tenant {
+            None => Ok(PLATFORM_TENANT),
+            Some(tenant) if tenant.enabled => Ok(tenant.uid),
+            Some(_) => Err(AppError::Other("租户已停用")),
+        }
+    }
+
+    /// 校验令牌中记录的租户存在且未停用
+    pub async fn check(app_state: &AppState, tenant_id: i64) -> Result<i64, AppError> {
+        if tenant_id == PLATFORM_TENANT {
+            return Ok(PLATFORM_TENANT);
+        }
+        match Self::get(app_state, tenant_id).await? {
+            None => Err(AppError::Other("未找到租户信息")),
+            Some(tenant) if tenant.enabled => Ok(tenant.uid),
+            Some(_) => Err(AppError::Other("租户已停用")),
+        }
+    }
+
+    /// 获取租户，优先使用缓存
+    pub async fn get(app_state: &AppState, tenant_id: i64) -> Result<Option<tenant::Model>, AppError> {
+        let key = RedisKey::tenant(tenant_id);
+        if app_state.exists(&key).await.is_ok() {
+            return app_state.cached::<tenant::Model>(&key).await.map(Some);
+        }
+        let tenant = Tenant::find_by_id(tenant_id)
+            .one(&app_state.db.connection)
+            .await?;
+        if let Some(tenant) = &tenant {
+            app_state.set_ex(&key, tenant.clone(), TENANT_CACHE_EXPIRE).await?;
+        }
+        Ok(tenant)
+    }
+
+    /// 租户变更后清除缓存
+    pub async fn evict(app_state: &AppState, tenant_id: i64) {
+        let _ = app_state.del(RedisKey::tenant(tenant_id)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::constant::RedisKey;
+    use crate::utils::tenant::{TenantUtils, PLATFORM_TENANT};
+    use std::path::{Path, PathBuf};
+
+    fn sources(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                sources(&path, files);
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn tenant_scope() {
+        assert_eq!(TenantUtils::current(), PLATFORM_TENANT);
+        assert_eq!(RedisKey::session("s1"), "rato:0:session:s1");
+        TenantUtils::scope(7, async {
+            assert_eq!(TenantUtils::current(), 7);
+            assert!(!TenantUtils::is_platform());
+            assert_eq!(RedisKey::session("s1"), "rato:7:session:s1");
+            assert_eq!(RedisKey::perm_version(), "rato:7:perm_version");
+            // 租户缓存不区分租户
+            assert_eq!(RedisKey::tenant(7), "rato:tenant:7");
+        })
+        .await;
+        assert!(TenantUtils::is_platform());
+    }
+
+    /// 按租户隔离的实体只能通过AppState的租户查询方法访问，直接使用实体的查询方法会跳过租户过滤
+    #[test]
+    fn tenant_queries() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = Vec::new();
+        sources(&root, &mut files);
+        // 实现了TenantEntity的实体名
+        let entities = files
+            .iter()
+            .filter(|path| path.parent().is_some_and(|dir| dir.ends_with("entity")))
+            .filter(|path| std::fs::read_to_string(path).unwrap().contains("impl TenantEntity for Entity"))
+            .map(|path| {
+                let stem = path.file_stem().unwrap().to_str().unwrap();
+                stem.split('_')
+                    .map(|word| word[..1].to_uppercase() + &word[1..])
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert!(entities.contains(&"User".to_string()));
+        let mut violations = Vec::new();
+        for path in files.iter().filter(|path| !path.ends_with("state/mod.rs")) {
+            let source = std::fs::read_to_string(path).unwrap();
+            // 测试代码仅用于生成SQL，不做检查
+            let source = source.split("#[cfg(test)]").next().unwrap();
+            let source = source.split_whitespace().collect::<String>();
+            for entity in &entities {
+                let patterns = [
+                    format!("{}::find()", entity),
+                    format!("{}::find_by_id(", entity),
+                    format!("{}::delete_by_id(", entity),
+                    format!("{}::update_many()", entity),
+                    format!("{}::delete_many()", entity),
+                    format!("find_related({})", entity),
+                    format!("find_also_related({})", entity),
+                    format!("find_with_related({})", entity),
+                    format!("load_one({}", entity),
+                    format!("load_many({}", entity),
+                    format!("load_many_to_many({}", entity),
+                ];
+                for pattern in patterns.iter().filter(|pattern| source.contains(pattern.as_str())) {
+                    violations.push(format!("{}: {}", path.strip_prefix(&root).unwrap().display(), pattern));
+                }
+            }
+        }
+        assert!(violations.is_empty(), "以下查询未按租户过滤：{:#?}", violations);
+    }
+}
@@ -0,0 +1,140 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+/// 树节点。序列化时节点字段与children平铺在同一层级
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode<T> {
+    #[serde(flatten)]
+    pub node: T,
+    pub children: Vec<TreeNode<T>>,
+}
+
+/// 树形结构工具类
+pub struct TreeUtils;
+
+impl TreeUtils {
+    /// 根据id及父id构建树。父节点不在列表中的节点作为根节点，同级节点保持原有顺序。
+    /// 数据本身成环时，环上最先出现的节点作为根节点，不丢弃任何节点
+    pub fn build<T, K, I, P>(items: Vec<T>, id: I, parent: P) -> Vec<TreeNode<T>>
+    where
+        K: Eq + Hash + Clone,
+        I: Fn(&T) -> K,
+        P: Fn(&T) -> K,
+    {
+        let ids = items.iter().map(&id).collect::<HashSet<_>>();
+        let mut children: HashMap<K, Vec<(usize, T)>> = HashMap::new();
+        let mut roots = Vec::new();
+        for (index, item) in items.into_iter().enumerate() {
+            let parent_id = parent(&item);
+            if ids.contains(&parent_id) && parent_id != id(&item) {
+                children.entry(parent_id).or_default().push((index, item));
+            } else {
+                roots.push(item);
+            }
+        }
+        let mut tree = roots
+            .into_iter()
+            .map(|item| Self::attach(item, &mut children, &id))
+            .collect::<Vec<_>>();
+        // 剩余的节点均在环上或挂在环上，逐个取出最先出现的节点作为根节点
+        while let Some((parent_id, position)) = children
+            .iter()
+            .flat_map(|(parent_id, nodes)| {
+                nodes
+                    .iter()
+                    .enumerate()
+                    .map(move |(position, (index, _))| (*index, parent_id, position))
+            })
+            .min_by_key(|(index, _, _)| *index)
+            .map(|(_, parent_id, position)| (parent_id.clone(), position))
+        {
+            let Some(nodes) = children.get_mut(&parent_id) else {
+                break;
+            };
+            let (_, item) = nodes.remove(position);
+            if nodes.is_empty() {
+                children.remove(&parent_id);
+            }
+            tree.push(Self::attach(item, &mut children, &id));
+        }
+        tree
+    }
+
+    /// 判断将节点的父节点修改为parent后是否成环。parents为节点id到父节点id的映射
+    pub fn has_cycle<K>(parents: &HashMap<K, K>, id: &K, parent: &K) -> bool
+    where
+        K: Eq + Hash + Clone,
+    {
+        let mut visited = HashSet::new();
+        let mut current = parent.clone();
+        loop {
+            if current == *id {
+                return true;
+            }
+            match parents.get(&current) {
+                Some(next) if visited.insert(current) => current = next.clone(),
+                _ => return false,
+            }
+        }
+    }
+
+    /// 递归挂载子节点。子节点取出后即从映射中移除，数据本身有环时也能结束
+    fn attach<T, K, I>(item: T, children: &mut HashMap<K, Vec<(usize, T)>>, id: &I) -> TreeNode<T>
+    where
+        K: Eq + Hash + Clone,
+        I: Fn(&T) -> K,
+    {
+        let nodes = children.remove(&id(&item)).unwrap_or_default();
+        TreeNode {
+            children: nodes
+                .into_iter()
+                .map(|(_, node)| Self::attach(node, children, id))
+                .collect(),
+            node: item,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::tree::{TreeNode, TreeUtils};
+    use std::collections::HashMap;
+
+    #[test]
+    fn build_tree() {
+        let items = vec![(1, 0), (2, 1), (3, 1), (4, 2), (5, 9)];
+        let tree = TreeUtils::build(items, |item| item.0, |item| item.1);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].node, (1, 0));
+        assert_eq!(tree[0].children.len(), 2);
+        assert_eq!(tree[0].children[0].children[0].node, (4, 2));
+        // 父节点不存在时作为根节点
+        assert_eq!(tree[1].node, (5, 9));
+    }
+
+    fn count<T>(nodes: &[TreeNode<T>]) -> usize {
+        nodes.iter().map(|node| 1 + count(&node.children)).sum()
+    }
+
+    #[test]
+    fn build_cycle() {
+        // 1、2互为父节点，3挂在环上，6为自身的父节点
+        let items = vec![(0, 9), (1, 2), (3, 1), (2, 1), (6, 6)];
+        let tree = TreeUtils::build(items, |item| item.0, |item| item.1);
+        assert_eq!(count(&tree), 5);
+        assert_eq!(tree.iter().map(|node| node.node).collect::<Vec<_>>(), vec![(0, 9), (6, 6), (1, 2)]);
+        assert_eq!(tree[2].children[0].node, (3, 1));
+        assert_eq!(tree[2].children[1].node, (2, 1));
+        assert!(tree[2].children[1].children.is_empty());
+    }
+
+    #[test]
+    fn check_cycle() {
+        let parents = HashMap::from([(1, 0), (2, 1), (3, 2), (4, 0)]);
+        assert!(TreeUtils::has_cycle(&parents, &1, &3));
+        assert!(TreeUtils::has_cycle(&parents, &2, &2));
+        assert!(!TreeUtils::has_cycle(&parents, &3, &4));
+        assert!(!TreeUtils::has_cycle(&parents, &1, &0));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::config::GlobalConfig;
+use crate::core::constant::RedisKey;
+use crate::core::error::AppError;
+use crate::entity::file;
+use crate::entity::file::{ChunkInitBody, ChunkUpload, ChunkUploadVo};
+use crate::state::AppState;
+use crate::utils::file::FileUtils;
+use chrono::Utc;
+use rato_core::redis::RedisPool;
+use redis::Script;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// 过期分片的清理间隔，单位秒
+const GC_INTERVAL: u64 = 60 * 60;
+
+/// 合并锁的有效期，单位秒。合并请求异常退出时到期自动释放
+const MERGE_LOCK_EXPIRE: u64 = 10 * 60;
+
+/// 清理用户已过期的任务后，进行中的任务数未达上限时登记新任务。返回1表示登记成功
+static SLOT_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+if redis.call('ZCARD', KEYS[1]) >= tonumber(ARGV[3]) then
+    return 0
+end
+redis.call('ZADD', KEYS[1], ARGV[2], ARGV[4])
+redis.call('EXPIRE', KEYS[1], ARGV[5])
+return 1
+"#,
+    )
+});
+
+/// 分片上传工具类。任务及已接收的分片序号保存在缓存中，分片保存在`{upload_dir}/chunks/{upload_id}`目录下
+pub struct ChunkUtils;
+
+impl ChunkUtils {
+    /// 创建分片上传任务
+    pub async fn init(
+        app_state: &AppState,
+        owner_id: i64,
+        body: ChunkInitBody,
+    ) -> Result<ChunkUploadVo, AppError> {
+        let env = &app_state.env;
+        if body.size == 0 || body.size > env.upload_max_size {
+            return Err(AppError::Other("文件大小超出限制"));
+        }
+        let sha256 = body.sha256.trim().to_ascii_lowercase();
+        if !Self::is_hex(&sha256, 64) {
+            return Err(AppError::Other("sha256格式错误"));
+        }
+        let chunk_size = Self::chunk_size(env);
+        let upload = ChunkUpload {
+            owner_id,
+            name: FileUtils::file_name(&body.name),
+            mime: body
+                .mime
+                .filter(|mime| !mime.trim().is_empty())
+                .unwrap_or(mime::APPLICATION_OCTET_STREAM.to_string()),
+            size: body.size,
+            sha256,
+            chunk_size,
+            chunks: Self::chunks(body.size, chunk_size),
+        };
+        let upload_id = uuid::Uuid::new_v4().simple().to_string();
+        // 限制单个用户同时进行的任务数，任务过期后自动释放名额
+        let now = Utc::now().timestamp();
+        let acquired = app_state
+            .eval::<_, _, i64>(
+                &SLOT_SCRIPT,
+                &[RedisKey::chunk_uploads(owner_id)],
+                &[
+                    now.to_string(),
+                    (now + env.upload_expire as i64).to_string(),
+                    env.upload_max_concurrent.to_string(),
+                    upload_id.clone(),
+                    env.upload_expire.to_string(),
+                ],
+            )
+            .await?;
+        if acquired != 1 {
+            return Err(AppError::Other("进行中的上传任务过多，请稍后再试"));
+        }
+        app_state
+            .set_ex(RedisKey::chunk_upload(&upload_id), upload.clone(), env.upload_expire)
+            .await?;
+        Ok(Self::vo(upload_id, &upload, vec![], env.upload_expire as i64))
+    }
+
+    /// 保存一个分片，重复上传同一分片时覆盖
+    pub async fn put(
+        app_state: &AppState,
+        owner_id: i64,
+        upload_id: &str,
+        index: u32,
+        bytes: &[u8],
+    ) -> Result<ChunkUploadVo, AppError> {
+        let upload = Self::get(app_state, owner_id, upload_id).await?;
+        let expected = Self::chunk_len(upload.size, upload.chunk_size, index)
+            .ok_or(AppError::Other("分片序号超出范围"))?;
+        if bytes.len() as u64 != expected {
+            return Err(AppError::Other("分片大小与预期不符"));
+        }
+        let dir = Self::dir(app_state, upload_id);
+        let written = match tokio::fs::create_dir_all(&dir).await {
+            Ok(_) => tokio::fs::write(dir.join(index.to_string()), bytes).await,
+            Err(e) => Err(e),
+        };
+        written.map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("保存分片失败")
+        })?;
+        // 分片集合与任务同时过期
+        let ttl = app_state.ttl(RedisKey::chunk_upload(upload_id)).await?;
+        let parts = RedisKey::chunk_parts(upload_id);
+        app_state.sadd(&parts, index).await?;
+        app_state.expire(&parts, ttl.max(1)).await?;
+        Self::status(app_state, owner_id, upload_id).await
+    }
+
+    /// 查询任务状态，客户端据此跳过已上传的分片
+    pub async fn status(
+        app_state: &AppState,
+        owner_id: i64,
+        upload_id: &str,
+    ) -> Result<ChunkUploadVo, AppError> {
+        let upload = Self::get(app_state, owner_id, upload_id).await?;
+        let mut received = app_state
+            .smembers::<_, u32>(RedisKey::chunk_parts(upload_id))
+            .await?;
+        received.sort_unstable();
+        let expires_in = app_state.ttl(RedisKey::chunk_upload(upload_id)).await?;
+        Ok(Self::vo(upload_id.to_string(), &upload, received, expires_in))
+    }
+
+    /// 合并分片并校验sha256，成功后写入文件记录。
+    /// 文件记录写入成功后才删除任务及分片，校验失败时可重新上传分片后再次合并
+    pub async fn complete(
+        app_state: &AppState,
+        owner_id: i64,
+        upload_id: &str,
+    ) -> Result<file::Model, AppError> {
+        let upload = Self::get(app_state, owner_id, upload_id).await?;
+        let parts = RedisKey::chunk_parts(upload_id);
+        let received = app_state.smembers::<_, u32>(&parts).await?;
+        if received.len() != upload.chunks as usize {
+            return Err(AppError::Other("分片未全部上传"));
+        }
+        // 合并期间持有锁，避免并发请求重复合并
+        let lock = RedisKey::chunk_lock(upload_id);
+        if !app_state.set_nx_ex(&lock, owner_id, MERGE_LOCK_EXPIRE).await? {
+            return Err(AppError::Other("上传任务正在合并，请稍后再试"));
+        }
+        let dir = Self::dir(app_state, upload_id);
+        let file = match Self::assemble(&dir, &upload).await {
+            Ok(path) => {
+                FileUtils::locked(app_state, &upload.sha256, async {
+                    app_state.storage.put_file(&upload.sha256, &path).await.map_err(|e| {
+                        tracing::error!("{:?}", e);
+                        AppError::Other("保存文件失败")
+                    })?;
+                    FileUtils::insert(
+                        app_state,
+                        owner_id,
+                        &upload.name,
+                        &upload.mime,
+                        upload.size as i64,
+                        upload.sha256.clone(),
+                    )
+                    .await
+                })
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if file.is_ok() {
+            // 先删除任务再释放锁，等待中的请求随后查询不到任务
+            let _ = app_state.del(RedisKey::chunk_upload(upload_id)).await;
+            let _ = app_state.del(&parts).await;
+            let _ = app_state.zrem(RedisKey::chunk_uploads(owner_id), upload_id).await;
+            let _ = tokio::fs::remove_dir_all(&dir).await;
+        } else {
+            let _ = tokio::fs::remove_file(dir.join("assembled")).await;
+        }
+        let _ = app_state.del(&lock).await;
+        file
+    }
+
+    /// 定期清理过期未完成的分片目录
+    pub async fn gc(upload_dir: PathBuf, expire: u64) {
+        let root = upload_dir.join("chunks");
+        let mut interval = tokio::time::interval(Duration::from_secs(GC_INTERVAL));
+        loop {
+            interval.tick().await;
+            match Self::sweep(&root, Duration::from_secs(expire)).await {
+                Ok(0) => {}
+                Ok(count) => tracing::info!("清理过期分片上传{}个", count),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => tracing::error!("清理过期分片失败：{:?}", e),
+            }
+        }
+    }
+
+    /// 删除修改时间早于有效期的分片目录。任务有效期自创建起计算，
+    /// 目录最后一次新增分片后超过有效期时任务必然已过期
+    async fn sweep(root: &Path, expire: Duration) -> io::Result<usize> {
+        let mut entries = tokio::fs::read_dir(root).await?;
+        let mut count = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let expired = entry
+                .metadata()
+                .await?
+                .modified()?
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed > expire);
+            if expired && tokio::fs::remove_dir_all(entry.path()).await.is_ok() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// 按序合并分片到临时文件并校验sha256，返回临时文件路径
+    async fn assemble(dir: &Path, upload: &ChunkUpload) -> Result<PathBuf, AppError> {
+        let path = dir.join("assembled");
+        let sha256 = async {
+            let mut output = tokio::fs::File::create(&path).await?;
+            let mut hasher = Sha256::new();
+            for index in 0..upload.chunks {
+                let bytes = tokio::fs::read(dir.join(index.to_string())).await?;
+                hasher.update(&bytes);
+                output.write_all(&bytes).await?;
+            }
+            output.sync_all().await?;
+            io::Result::Ok(
+                hasher
+                    .finalize()
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>(),
+            )
+        }
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            AppError::Other("合并分片失败")
+        })?;
+        if sha256 != upload.sha256 {
+            return Err(AppError::Other("文件校验失败，请重新上传"));
+        }
+        Ok(path)
+    }
+
+    /// 查询任务，仅任务创建人可以访问
+    async fn get(
+        app_state: &AppState,
+        owner_id: i64,
+        upload_id: &str,
+    ) -> Result<ChunkUpload, AppError> {
+        if !Self::is_hex(upload_id, 32) {
+            return Err(AppError::Other("上传任务不存在或已过期"));
+        }
+        let key = RedisKey::chunk_upload(upload_id);
+        app_state
+            .exists(&key)
+            .await
+            .map_err(|_| AppError::Other("上传任务不存在或已过期"))?;
+        let upload = app_state.cached::<ChunkUpload>(&key).await?;
+        if upload.owner_id != owner_id {
+            return Err(AppError::Other("上传任务不存在或已过期"));
+        }
+        Ok(upload)
+    }
+
+    /// 分片大小，不超过单次请求的上限
+    fn chunk_size(env: &GlobalConfig) -> u64 {
+        env.upload_chunk_size.clamp(1, env.body_limit as u64)
+    }
+
+    /// 分片数量
+    pub fn chunks(size: u64, chunk_size: u64) -> u32 {
+        size.div_ceil(chunk_size) as u32
+    }
+
+    /// 第index个分片的字节数，序号超出范围时返回None
+    pub fn chunk_len(size: u64, chunk_size: u64, index: u32) -> Option<u64> {
+        let start = (index as u64).checked_mul(chunk_size)?;
+        (start < size).then(|| chunk_size.min(size - start))
+    }
+
+    fn dir(app_state: &AppState, upload_id: &str) -> PathBuf {
+        Path::new(&app_state.env.upload_dir)
+            .join("chunks")
+            .join(upload_id)
+    }
+
+    fn is_hex(value: &str, len: usize) -> bool {
+        value.len() == len && value.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+
+    fn vo(upload_id: String, upload: &ChunkUpload, received: Vec<u32>, expires_in: i64) -> ChunkUploadVo {
+        ChunkUploadVo {
+            upload_id,
+            chunk_size: upload.chunk_size,
+            chunks: upload.chunks,
+            received,
+            expires_in,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::upload::ChunkUtils;
+
+    #[test]
+    fn chunk_len() {
+        assert_eq!(ChunkUtils::chunks(10, 4), 3);
+        assert_eq!(ChunkUtils::chunks(8, 4), 2);
+        assert_eq!(ChunkUtils::chunk_len(10, 4, 0), Some(4));
+        assert_eq!(ChunkUtils::chunk_len(10, 4, 2), Some(2));
+        assert_eq!(ChunkUtils::chunk_len(10, 4, 3), None);
+        assert_eq!(ChunkUtils::chunk_len(8, 4, 1), Some(4));
+        assert_eq!(ChunkUtils::chunk_len(8, 4, 2), None);
+        assert_eq!(ChunkUtils::chunk_len(u64::MAX, u64::MAX, u32::MAX), None);
+    }
+}